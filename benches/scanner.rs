@@ -0,0 +1,86 @@
+//! Scanner and compiler throughput on a large generated script.
+//!
+//! Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rlox::parser::compile;
+use rlox::scanner::Scanner;
+
+const TARGET_BYTES: usize = 4 * 1024 * 1024;
+const ITERATIONS: u32 = 10;
+
+/// A chunk of typical Lox, repeated until the source is big enough.
+const SNIPPET: &str = r#"
+/// Computes something vaguely useful.
+fun compute_total(items, rate) {
+    var total = 0;
+    for (var i = 0; i < items; i = i + 1) {
+        /* apply the rate, then round
+           down a little */
+        total = total + i * rate - 0.5e-3;
+        if (total > 1_000_000 or total < -0x7fff) break;
+    }
+    return total;
+}
+
+class Account < Base {
+    init(owner, balance) {
+        this.owner = owner;
+        this.balance = balance;
+    }
+
+    describe() {
+        return "owner: ${this.owner}, balance: ${this.balance}\n";
+    }
+}
+
+var report = Account("someone with a long name", 12345.678).describe();
+print report + "done";
+"#;
+
+fn generate() -> String {
+    let mut source = String::with_capacity(TARGET_BYTES + SNIPPET.len());
+    while source.len() < TARGET_BYTES {
+        source.push_str(SNIPPET);
+    }
+    source
+}
+
+fn bench(name: &str, bytes: usize, mut f: impl FnMut()) {
+    // warm up once so the first iteration doesn't pay for page faults
+    f();
+    let mut best = Duration::MAX;
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        f();
+        let elapsed = start.elapsed();
+        best = best.min(elapsed);
+        total += elapsed;
+    }
+    let mb = bytes as f64 / (1024.0 * 1024.0);
+    println!(
+        "{:<24} best {:>8.2?}  mean {:>8.2?}  {:>8.1} MiB/s",
+        name,
+        best,
+        total / ITERATIONS,
+        mb / best.as_secs_f64(),
+    );
+}
+
+fn main() {
+    let source = generate();
+    println!("input: {} bytes", source.len());
+
+    bench("scan (lazy iterator)", source.len(), || {
+        black_box(Scanner::new(black_box(&source)).count());
+    });
+    bench("scan (collect tokens)", source.len(), || {
+        black_box(Scanner::new(black_box(&source)).scan_tokens());
+    });
+    bench("compile", source.len(), || {
+        black_box(compile(black_box(&source)).is_ok());
+    });
+}
@@ -1,30 +1,324 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::rc::Rc;
+
 use crate::op::Op;
+use crate::token::Span;
+use crate::value::Value;
 
+#[derive(Debug)]
 pub struct Chunk {
-    pub code: Vec<Op>,
-    lines: Vec<i32>,
+    pub code: Vec<u8>,
+    pub constants: Vec<Value>,
+    /// Run-length encoded source locations: each run starts at a code
+    /// offset and covers every byte up to the next run.
+    spans: Vec<SpanRun>,
+    /// Doc comments on the declarations compiled into this chunk.
+    pub docs: Vec<Doc>,
+    /// The source the chunk was compiled from, to quote in runtime errors.
+    pub source: Rc<str>,
+    /// Names of the global slots the code uses. The VM only needs the
+    /// slots; this is for the disassembler.
+    pub global_names: HashMap<u16, Rc<str>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SpanRun {
+    start: usize,
+    span: Span,
+}
+
+/// What kind of declaration a doc comment is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocTarget {
+    Function,
+    Class,
+    Method,
+    Variable,
+}
+
+/// The `///` comments directly before a declaration. Methods are named
+/// `Class.method`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Doc {
+    pub target: DocTarget,
+    pub name: String,
+    pub text: String,
+    pub line: usize,
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chunk {
     pub fn new() -> Chunk {
         Chunk {
             code: Vec::new(),
-            lines: Vec::new(),
+            constants: Vec::new(),
+            spans: Vec::new(),
+            docs: Vec::new(),
+            source: Rc::from(""),
+            global_names: HashMap::new(),
         }
     }
-    pub fn write_chunk(&mut self, op: Op, line: i32) {
-        self.code.push(op);
-        self.lines.push(line);
+
+    /// Appends `byte`, compiled from the code at `span`.
+    pub fn write_byte(&mut self, byte: u8, span: Span) {
+        if self.spans.last().is_none_or(|run| run.span != span) {
+            self.spans.push(SpanRun {
+                start: self.code.len(),
+                span,
+            });
+        }
+        self.code.push(byte);
+    }
+
+    pub fn write_op(&mut self, op: Op, span: Span) {
+        self.write_byte(op as u8, span);
+    }
+
+    /// Adds `value` to the constant pool and returns its index.
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    /// The source location the byte at `offset` was compiled from.
+    pub fn span(&self, offset: usize) -> Span {
+        let run = self.spans.partition_point(|run| run.start <= offset);
+        run.checked_sub(1).map_or_else(Span::default, |run| self.spans[run].span)
+    }
+
+    pub fn line(&self, offset: usize) -> usize {
+        self.span(offset).line
+    }
+
+    pub fn read_u16(&self, offset: usize) -> Option<usize> {
+        let bytes = self.code.get(offset..offset + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+    }
+
+    pub fn read_u24(&self, offset: usize) -> Option<usize> {
+        let bytes = self.code.get(offset..offset + 3)?;
+        Some(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]) as usize)
     }
 
     pub fn dissassemble_chunk(&self, name: &str) {
-        println!("== {} ==", name);
-        for (i, op) in self.code.iter().enumerate() {
-            self.dissassemble_instruction(i, op);
+        print!("{}", self.disassemble(name));
+    }
+
+    /// The listing `dissassemble_chunk` prints: this chunk's instructions,
+    /// then those of every function nested in it.
+    pub fn disassemble(&self, name: &str) -> String {
+        let mut out = String::new();
+        self.write_chunk(name, &mut out).expect("writing to a String can't fail");
+        out
+    }
+
+    fn write_chunk(&self, name: &str, out: &mut impl Write) -> fmt::Result {
+        writeln!(out, "== {} ==", name)?;
+        let mut offset = 0;
+        while offset < self.code.len() {
+            offset = self.write_instruction(offset, out)?;
         }
+
+        for constant in &self.constants {
+            if let Value::Function(function) = constant {
+                writeln!(out)?;
+                function.chunk.write_chunk(&function.name, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the instruction at `offset` and returns the offset of the
+    /// next one.
+    pub fn dissassemble_instruction(&self, offset: usize) -> usize {
+        let mut out = String::new();
+        let next = self.write_instruction(offset, &mut out).expect("writing to a String can't fail");
+        print!("{}", out);
+        next
+    }
+
+    fn write_instruction(&self, offset: usize, out: &mut impl Write) -> Result<usize, fmt::Error> {
+        if offset > 0 && self.line(offset) == self.line(offset - 1) {
+            write!(out, "{:04} {:>4} | ", offset, "|")?;
+        } else {
+            write!(out, "{:04} {:>4} | ", offset, self.line(offset))?;
+        }
+
+        let Some(op) = Op::from_byte(self.code[offset]) else {
+            writeln!(out, "unknown opcode {}", self.code[offset])?;
+            return Ok(offset + 1);
+        };
+        let (operand, next) = self.operand(op, offset + 1);
+        match operand {
+            Some(operand) => writeln!(out, "{:>20} | {}", op, operand)?,
+            None => writeln!(out, "{:>20} |", op)?,
+        }
+        Ok(next)
+    }
+
+    /// Formats the operands of `op`, which start at `offset`, and returns
+    /// them with the offset of the next instruction.
+    fn operand(&self, op: Op, offset: usize) -> (Option<String>, usize) {
+        let constant = |index: Option<usize>| match index.and_then(|i| self.constants.get(i)) {
+            Some(Value::String(s)) if op != Op::Constant && op != Op::ConstantLong => format!("{:?}", s),
+            // constants never refer to heap objects
+            Some(Value::Number(n)) => format!("{:>4} '{}'", index.unwrap(), n),
+            Some(Value::String(s)) => format!("{:>4} '{}'", index.unwrap(), s),
+            Some(Value::Function(function)) => format!("{:>4} '{}'", index.unwrap(), function),
+            Some(value) => format!("{:>4} '{:?}'", index.unwrap(), value),
+            None => "<invalid constant>".to_string(),
+        };
+        let byte = self.code.get(offset).map(|b| *b as usize);
+
+        match op {
+            Op::Constant => (Some(constant(byte)), offset + 1),
+            Op::ConstantLong => (Some(constant(self.read_u24(offset))), offset + 3),
+            Op::GetLocal | Op::SetLocal | Op::GetUpvalue | Op::SetUpvalue | Op::Call => {
+                (Some(format!("{:?}", byte.unwrap_or_default())), offset + 1)
+            }
+            Op::GetGlobal | Op::DefineGlobal | Op::SetGlobal => {
+                let slot = self.read_u16(offset).unwrap_or_default() as u16;
+                let operand = match self.global_names.get(&slot) {
+                    Some(name) => format!("{:>4} '{}'", slot, name),
+                    None => format!("{:>4}", slot),
+                };
+                (Some(operand), offset + 2)
+            }
+            Op::GetProperty
+            | Op::SetProperty
+            | Op::GetSuper
+            | Op::Class
+            | Op::Method => (Some(constant(self.read_u16(offset))), offset + 2),
+            Op::Jump | Op::JumpIfFalse | Op::JumpIfTrue | Op::Loop => {
+                let jump = self.read_u16(offset).unwrap_or_default();
+                let target = if op == Op::Loop {
+                    (offset + 2).wrapping_sub(jump)
+                } else {
+                    offset + 2 + jump
+                };
+                (Some(format!("{} -> {:04}", jump, target)), offset + 2)
+            }
+            Op::Closure => {
+                let index = self.read_u16(offset);
+                let mut next = offset + 2;
+                let Some(Value::Function(function)) = index.and_then(|i| self.constants.get(i)) else {
+                    return (Some("<invalid function>".to_string()), next);
+                };
+                let mut operand = function.to_string();
+                for _ in 0..function.upvalue_count {
+                    let is_local = self.code.get(next).copied().unwrap_or_default() == 1;
+                    let index = self.code.get(next + 1).copied().unwrap_or_default();
+                    let kind = if is_local { "local" } else { "upvalue" };
+                    operand.push_str(&format!(" ({} {})", kind, index));
+                    next += 2;
+                }
+                (Some(operand), next)
+            }
+            _ => (None, offset),
+        }
+    }
+}
+
+#[cfg(test)]
+fn span_on(line: usize) -> Span {
+    Span { line, column: 1, start: 0, end: 1 }
+}
+
+#[cfg(test)]
+fn write_u16(chunk: &mut Chunk, value: u16, span: Span) {
+    let [high, low] = value.to_be_bytes();
+    chunk.write_byte(high, span);
+    chunk.write_byte(low, span);
+}
+
+#[test]
+fn test_constants_past_a_byte_use_constant_long() {
+    let mut source = String::new();
+    for i in 0..300 {
+        source.push_str(&format!("print {};\n", i));
+    }
+    let chunk = crate::parser::compile(&source).unwrap();
+
+    // walk the code, reading each constant back through its operand
+    let mut printed = Vec::new();
+    let mut long = 0;
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        let op = Op::from_byte(chunk.code[offset]).unwrap();
+        let index = match op {
+            Op::Constant => Some(chunk.code[offset + 1] as usize),
+            Op::ConstantLong => {
+                long += 1;
+                chunk.read_u24(offset + 1)
+            }
+            _ => None,
+        };
+        if let Some(Value::Number(n)) = index.map(|i| &chunk.constants[i]) {
+            printed.push(*n);
+        }
+        offset = chunk.operand(op, offset + 1).1;
     }
+    assert_eq!(printed, (0..300).map(|i| i as f64).collect::<Vec<_>>());
+    assert_eq!(long, 300 - 256);
+}
 
-    pub fn dissassemble_instruction(&self, offset: usize, op: &Op) {
-        println!("{:04} | {:>16}", offset, op);
+#[test]
+fn test_spans_are_run_length_encoded() {
+    let mut chunk = Chunk::new();
+    let lines = [1, 1, 2, 2, 2, 5, 7, 7];
+    for (i, line) in lines.iter().enumerate() {
+        chunk.write_byte(i as u8, span_on(*line));
     }
+    assert_eq!(chunk.spans.len(), 4);
+    for (offset, line) in lines.iter().enumerate() {
+        assert_eq!(chunk.line(offset), *line, "offset {}", offset);
+    }
+    // past the end, the last run continues
+    assert_eq!(chunk.line(lines.len()), 7);
+
+    let wide = Span { line: 7, column: 3, start: 10, end: 14 };
+    chunk.write_byte(0, wide);
+    assert_eq!(chunk.span(lines.len()), wide);
+    assert_eq!(chunk.span(lines.len() - 1), span_on(7));
+    assert_eq!(Chunk::new().span(0), Span::default());
+}
+
+#[test]
+fn test_disassembly() {
+    let mut chunk = Chunk::new();
+    let number = chunk.add_constant(Value::Number(1.5)) as u8;
+    let name = chunk.add_constant(Value::String("x".into())) as u16;
+    // slot 1 has no name, as if the chunk was built by hand
+    chunk.global_names.insert(0, "x".into());
+    chunk.write_op(Op::Constant, span_on(1));
+    chunk.write_byte(number, span_on(1));
+    chunk.write_op(Op::DefineGlobal, span_on(1));
+    write_u16(&mut chunk, 0, span_on(1));
+    chunk.write_op(Op::GetGlobal, span_on(2));
+    write_u16(&mut chunk, 1, span_on(2));
+    chunk.write_op(Op::GetProperty, span_on(2));
+    write_u16(&mut chunk, name, span_on(2));
+    chunk.write_op(Op::JumpIfFalse, span_on(3));
+    write_u16(&mut chunk, 1, span_on(3));
+    chunk.write_op(Op::Print, span_on(3));
+    chunk.write_op(Op::Return, span_on(4));
+
+    let expected = [
+        "== test ==",
+        "0000    1 |          OP_CONSTANT |    0 '1.5'",
+        "0002    | |     OP_DEFINE_GLOBAL |    0 'x'",
+        "0005    2 |        OP_GET_GLOBAL |    1",
+        "0008    | |      OP_GET_PROPERTY | \"x\"",
+        "0011    3 |     OP_JUMP_IF_FALSE | 1 -> 0015",
+        "0014    | |             OP_PRINT |",
+        "0015    4 |            OP_RETURN |",
+        "",
+    ];
+    assert_eq!(chunk.disassemble("test"), expected.join("\n"));
 }
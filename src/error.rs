@@ -0,0 +1,168 @@
+use std::fmt::{Display, Formatter};
+
+use crate::token::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompileErrorKind {
+    UnexpectedCharacter,
+    UnterminatedString,
+    UnterminatedComment,
+    InvalidEscape,
+    InvalidNumber,
+    UnexpectedToken,
+    ExpectedExpression,
+    InvalidAssignmentTarget,
+    DuplicateVariable,
+    UninitializedLocal,
+    TooManyArguments,
+    TooManyConstants,
+    TooManyGlobals,
+    TooManyLocals,
+    TooManyUpvalues,
+    TooMuchNesting,
+    InvalidReturn,
+    InvalidThis,
+    InvalidSuper,
+    InvalidSuperclass,
+    JumpTooLarge,
+    InvalidBreak,
+    InvalidContinue,
+}
+
+/// What a compile error points at, used to render the "at ..." part of the
+/// message the way clox does.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorAt {
+    Token(String),
+    End,
+    Nowhere,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileError {
+    pub kind: CompileErrorKind,
+    pub message: String,
+    pub span: Span,
+    pub at: ErrorAt,
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "[line {}] Error", self.span.line)?;
+        match &self.at {
+            ErrorAt::Token(lexeme) => write!(f, " at '{}'", lexeme)?,
+            ErrorAt::End => write!(f, " at end")?,
+            ErrorAt::Nowhere => {}
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// A call that was active when a runtime error happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+    /// Empty for top-level code.
+    pub function: String,
+    pub line: usize,
+}
+
+impl Display for StackFrame {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self.function.as_str() {
+            "" => write!(f, "[line {}] in script", self.line),
+            name => write!(f, "[line {}] in {}()", self.line, name),
+        }
+    }
+}
+
+/// An error raised while running a program. Formats like clox: the message,
+/// then the stack trace, innermost call first, followed by the offending
+/// source line with the failing code underlined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    /// The code of the instruction that failed.
+    pub span: Span,
+    pub trace: Vec<StackFrame>,
+    /// The source line `span` starts on, empty if the source isn't known.
+    pub source_line: String,
+}
+
+impl RuntimeError {
+    pub fn line(&self) -> usize {
+        self.span.line
+    }
+
+    /// The source line with a caret under the failing code, or `None` if
+    /// the source isn't known.
+    pub fn excerpt(&self) -> Option<String> {
+        if self.source_line.is_empty() || self.span.column == 0 {
+            return None;
+        }
+        let line = self.source_line.as_str();
+        let column = line
+            .char_indices()
+            .nth(self.span.column - 1)
+            .map_or(line.len(), |(i, _)| i);
+        let end = (column + self.span.end.saturating_sub(self.span.start)).min(line.len());
+        let width = line.get(column..end).map_or(0, |code| code.chars().count()).max(1);
+        // keep tabs so the caret lines up however they're rendered
+        let indent: String = line[..column]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let number = self.span.line.to_string();
+        let gutter = " ".repeat(number.len());
+        Some(format!(
+            "{} | {}\n{} | {}{}",
+            number,
+            line,
+            gutter,
+            indent,
+            "^".repeat(width)
+        ))
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        for frame in &self.trace {
+            write!(f, "\n{}", frame)?;
+        }
+        if let Some(excerpt) = self.excerpt() {
+            write!(f, "\n{}", excerpt)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+#[derive(Debug)]
+pub enum LoxError {
+    Compile(Vec<CompileError>),
+    Runtime(RuntimeError),
+}
+
+impl Display for LoxError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            LoxError::Compile(errors) => {
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", e)?;
+                }
+                Ok(())
+            }
+            LoxError::Runtime(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for LoxError {}
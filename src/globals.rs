@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::value::Value;
+
+/// Global variables, addressed by slot.
+///
+/// The compiler gives every global name a slot the first time it sees it, so
+/// the VM can access globals by index instead of hashing names. A slot stays
+/// undefined until a `var`, `fun` or `class` declaration runs, which keeps
+/// globals late-bound: a function may mention a global declared after it.
+#[derive(Debug, Default)]
+pub struct Globals {
+    slots: HashMap<Rc<str>, u16>,
+    names: Vec<Rc<str>>,
+    values: Vec<Option<Value>>,
+}
+
+impl Globals {
+    pub fn new() -> Self {
+        Globals {
+            slots: HashMap::new(),
+            names: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Returns the slot for `name`, allocating an undefined one if needed.
+    /// Returns `None` once every u16 slot is taken.
+    pub fn resolve(&mut self, name: &Rc<str>) -> Option<u16> {
+        if let Some(slot) = self.slots.get(name) {
+            return Some(*slot);
+        }
+        let slot = u16::try_from(self.names.len()).ok()?;
+        self.slots.insert(Rc::clone(name), slot);
+        self.names.push(Rc::clone(name));
+        self.values.push(None);
+        Some(slot)
+    }
+
+    /// The name the slot was allocated for.
+    pub fn name(&self, slot: usize) -> Option<&str> {
+        self.names.get(slot).map(|name| &**name)
+    }
+
+    /// The value of a defined global.
+    pub fn get(&self, slot: usize) -> Option<&Value> {
+        self.values.get(slot)?.as_ref()
+    }
+
+    /// Defines or redefines the global. Returns false if the slot was never
+    /// allocated.
+    pub fn define(&mut self, slot: usize, value: Value) -> bool {
+        match self.values.get_mut(slot) {
+            Some(global) => {
+                *global = Some(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Assigns to an already defined global. Returns false if it's undefined.
+    pub fn set(&mut self, slot: usize, value: Value) -> bool {
+        match self.values.get_mut(slot) {
+            Some(Some(global)) => {
+                *global = value;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Values of the defined globals.
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.values.iter().flatten()
+    }
+
+    /// Looks a global up by name rather than slot.
+    pub fn lookup(&self, name: &str) -> Option<&Value> {
+        let slot = *self.slots.get(name)?;
+        self.get(slot as usize)
+    }
+}
+
+#[test]
+fn test_slots_are_stable_and_start_undefined() {
+    let mut globals = Globals::new();
+    let a: Rc<str> = Rc::from("a");
+    let b: Rc<str> = Rc::from("b");
+
+    assert_eq!(globals.resolve(&a), Some(0));
+    assert_eq!(globals.resolve(&b), Some(1));
+    assert_eq!(globals.resolve(&Rc::from("a")), Some(0));
+    assert_eq!(globals.name(1), Some("b"));
+
+    assert!(globals.get(0).is_none());
+    assert!(!globals.set(0, Value::Nil));
+    assert!(globals.define(0, Value::Number(1.0)));
+    assert!(globals.set(0, Value::Number(2.0)));
+    assert!(matches!(globals.lookup("a"), Some(Value::Number(n)) if *n == 2.0));
+    assert!(!globals.define(2, Value::Nil));
+}
@@ -0,0 +1,394 @@
+use std::fmt::{Display, Formatter};
+use std::mem::size_of;
+
+use crate::object::{BoundMethod, Class, Closure, Instance, Upvalue};
+use crate::value::Value;
+
+/// Bytes that may be allocated before the first collection.
+const INITIAL_THRESHOLD: usize = 1024 * 1024;
+/// After a collection, the next one happens once the heap has grown by this
+/// factor.
+const GROWTH_FACTOR: usize = 2;
+
+/// Reference to an object on the `Heap`. Handles are only meaningful for
+/// the heap that allocated them. Once the object is collected its slot may
+/// be reused, but the slot's generation changes, so an old handle never
+/// refers to the new object: `Heap::get` returns `None` for it instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+/// Runtime objects that can refer to each other, and so can form cycles.
+#[derive(Debug)]
+pub enum Obj {
+    Closure(Closure),
+    Upvalue(Upvalue),
+    Class(Class),
+    Instance(Instance),
+    BoundMethod(BoundMethod),
+}
+
+impl Obj {
+    /// Rough number of bytes the object owns, used to decide when to collect.
+    fn size(&self) -> usize {
+        size_of::<Obj>() + match self {
+            Obj::Closure(closure) => closure.upvalues.len() * size_of::<Handle>(),
+            _ => 0,
+        }
+    }
+}
+
+/// Owns every runtime object and frees the unreachable ones with a tracing
+/// mark-and-sweep collector.
+///
+/// The heap doesn't know the roots: the VM marks them with `mark` and
+/// `mark_value`, then calls `collect`, which traces everything reachable
+/// from them and frees the rest.
+#[derive(Debug)]
+pub struct Heap {
+    objects: Vec<Option<Obj>>,
+    marks: Vec<bool>,
+    /// Bumped every time a slot is freed, to tell its objects apart.
+    generations: Vec<u32>,
+    /// Indices of freed slots, reused before the heap grows.
+    free: Vec<u32>,
+    /// Marked objects whose references haven't been traced yet.
+    gray: Vec<Handle>,
+    live: usize,
+    bytes_allocated: usize,
+    threshold: usize,
+    next_gc: usize,
+    growth_factor: usize,
+    stress: bool,
+    allocated_since_gc: bool,
+    collections: usize,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Typed lookups for handles the VM knows are live, i.e. reachable from its
+/// roots. They panic on a stale handle or one to another kind of object;
+/// use `Heap::get` for handles that may have been collected.
+macro_rules! accessors {
+    ($($variant:ident: $get:ident, $get_mut:ident -> $ty:ty;)*) => {
+        $(
+            pub fn $get(&self, handle: Handle) -> &$ty {
+                match self.live(handle) {
+                    Obj::$variant(obj) => obj,
+                    obj => panic!("{:?} is not a {}: {:?}", handle, stringify!($variant), obj),
+                }
+            }
+
+            pub fn $get_mut(&mut self, handle: Handle) -> &mut $ty {
+                match self.live_mut(handle) {
+                    Obj::$variant(obj) => obj,
+                    obj => panic!("{:?} is not a {}: {:?}", handle, stringify!($variant), obj),
+                }
+            }
+        )*
+    };
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Heap {
+            objects: Vec::new(),
+            marks: Vec::new(),
+            generations: Vec::new(),
+            free: Vec::new(),
+            gray: Vec::new(),
+            live: 0,
+            bytes_allocated: 0,
+            threshold: INITIAL_THRESHOLD,
+            next_gc: INITIAL_THRESHOLD,
+            growth_factor: GROWTH_FACTOR,
+            stress: false,
+            allocated_since_gc: false,
+            collections: 0,
+        }
+    }
+
+    /// Sets how many bytes may be allocated before collecting. The heap never
+    /// waits for less than this, however small it is after a collection.
+    pub fn set_threshold(&mut self, bytes: usize) {
+        self.threshold = bytes;
+        self.next_gc = bytes.max(self.bytes_allocated * self.growth_factor);
+    }
+
+    /// Sets how much the heap may grow after a collection before the next.
+    pub fn set_growth_factor(&mut self, factor: usize) {
+        self.growth_factor = factor.max(1);
+    }
+
+    /// In stress mode the VM collects whenever anything was allocated, so
+    /// objects that aren't properly rooted are freed as early as possible.
+    pub fn set_stress(&mut self, stress: bool) {
+        self.stress = stress;
+    }
+
+    pub fn alloc(&mut self, obj: Obj) -> Handle {
+        self.bytes_allocated += obj.size();
+        self.live += 1;
+        self.allocated_since_gc = true;
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.objects[index as usize] = Some(obj);
+                index
+            }
+            None => {
+                let index = u32::try_from(self.objects.len()).expect("heap exhausted");
+                self.objects.push(Some(obj));
+                self.marks.push(false);
+                self.generations.push(0);
+                index
+            }
+        };
+        Handle {
+            index,
+            generation: self.generations[index as usize],
+        }
+    }
+
+    pub fn should_collect(&self) -> bool {
+        if self.stress {
+            self.allocated_since_gc
+        } else {
+            self.bytes_allocated > self.next_gc
+        }
+    }
+
+    /// Whether `handle` still refers to a live object.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.generations.get(handle.index as usize) == Some(&handle.generation)
+    }
+
+    /// The object `handle` refers to, or `None` if it was collected.
+    pub fn get(&self, handle: Handle) -> Option<&Obj> {
+        if !self.contains(handle) {
+            return None;
+        }
+        self.objects[handle.index as usize].as_ref()
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut Obj> {
+        if !self.contains(handle) {
+            return None;
+        }
+        self.objects[handle.index as usize].as_mut()
+    }
+
+    fn live(&self, handle: Handle) -> &Obj {
+        self.get(handle).unwrap_or_else(|| panic!("stale handle {:?}", handle))
+    }
+
+    fn live_mut(&mut self, handle: Handle) -> &mut Obj {
+        self.get_mut(handle).unwrap_or_else(|| panic!("stale handle {:?}", handle))
+    }
+
+    accessors! {
+        Closure: closure, closure_mut -> Closure;
+        Upvalue: upvalue, upvalue_mut -> Upvalue;
+        Class: class, class_mut -> Class;
+        Instance: instance, instance_mut -> Instance;
+        BoundMethod: bound_method, bound_method_mut -> BoundMethod;
+    }
+
+    /// Number of live objects.
+    pub fn len(&self) -> usize {
+        self.live
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live == 0
+    }
+
+    pub fn bytes_allocated(&self) -> usize {
+        self.bytes_allocated
+    }
+
+    /// Number of collections so far.
+    pub fn collections(&self) -> usize {
+        self.collections
+    }
+
+    /// Marks a root. Stale handles are ignored.
+    pub fn mark(&mut self, handle: Handle) {
+        if self.contains(handle) {
+            Gray { marks: &mut self.marks, gray: &mut self.gray }.mark(handle);
+        }
+    }
+
+    /// Marks the object `value` refers to, if any, as a root.
+    pub fn mark_value(&mut self, value: &Value) {
+        if let Some(handle) = value.handle() {
+            self.mark(handle);
+        }
+    }
+
+    /// Frees every object not reachable from the roots marked since the last
+    /// collection.
+    pub fn collect(&mut self) {
+        self.trace();
+        self.sweep();
+        self.next_gc = self.threshold.max(self.bytes_allocated * self.growth_factor);
+        self.allocated_since_gc = false;
+        self.collections += 1;
+    }
+
+    fn trace(&mut self) {
+        let mut gray = Gray { marks: &mut self.marks, gray: &mut self.gray };
+        while let Some(handle) = gray.gray.pop() {
+            match &self.objects[handle.index as usize] {
+                Some(Obj::Closure(closure)) => {
+                    for upvalue in &closure.upvalues {
+                        gray.mark(*upvalue);
+                    }
+                }
+                Some(Obj::Upvalue(Upvalue::Closed(value))) => gray.mark_value(value),
+                Some(Obj::Upvalue(Upvalue::Open(_))) => {}
+                Some(Obj::Class(class)) => {
+                    for (_, method) in class.methods.iter() {
+                        gray.mark_value(method);
+                    }
+                }
+                Some(Obj::Instance(instance)) => {
+                    gray.mark(instance.class);
+                    for (_, field) in instance.fields.iter() {
+                        gray.mark_value(field);
+                    }
+                }
+                Some(Obj::BoundMethod(bound)) => {
+                    gray.mark_value(&bound.receiver);
+                    gray.mark(bound.method);
+                }
+                None => panic!("dangling handle {:?}", handle),
+            }
+        }
+    }
+
+    fn sweep(&mut self) {
+        for (index, slot) in self.objects.iter_mut().enumerate() {
+            if std::mem::take(&mut self.marks[index]) {
+                continue;
+            }
+            if let Some(obj) = slot.take() {
+                self.bytes_allocated -= obj.size();
+                self.live -= 1;
+                self.generations[index] = self.generations[index].wrapping_add(1);
+                self.free.push(index as u32);
+            }
+        }
+    }
+
+    /// Formats `value`, looking up the objects it refers to.
+    pub fn display<'a>(&'a self, value: &'a Value) -> Displayed<'a> {
+        Displayed { heap: self, value }
+    }
+}
+
+/// The marking half of the tri-color collector, split from `Heap` so that
+/// objects can be traced while they're borrowed.
+struct Gray<'a> {
+    marks: &'a mut Vec<bool>,
+    gray: &'a mut Vec<Handle>,
+}
+
+impl Gray<'_> {
+    fn mark(&mut self, handle: Handle) {
+        let marked = &mut self.marks[handle.index as usize];
+        if !*marked {
+            *marked = true;
+            self.gray.push(handle);
+        }
+    }
+
+    fn mark_value(&mut self, value: &Value) {
+        if let Some(handle) = value.handle() {
+            self.mark(handle);
+        }
+    }
+}
+
+/// How values whose object was collected are displayed.
+const COLLECTED: &str = "<collected>";
+
+/// A `Value` paired with the heap its handles point into.
+pub struct Displayed<'a> {
+    heap: &'a Heap,
+    value: &'a Value,
+}
+
+impl Display for Displayed<'_> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let heap = self.heap;
+        // values held by the host may outlive their objects
+        let Some(obj) = self.value.handle().map(|handle| heap.get(handle)) else {
+            return match self.value {
+                Value::Number(n) => write!(f, "{}", n),
+                Value::Bool(b) => write!(f, "{}", b),
+                Value::String(s) => write!(f, "{}", s),
+                Value::Function(fun) => write!(f, "{}", fun),
+                Value::NativeFn(_) => write!(f, "<native fn>"),
+                Value::Nil => write!(f, "nil"),
+                _ => unreachable!("{:?} has a handle", self.value),
+            };
+        };
+        let class_name = |class: Handle| match heap.get(class) {
+            Some(Obj::Class(class)) => class.name.as_str(),
+            _ => COLLECTED,
+        };
+        let function = |closure: Handle| match heap.get(closure) {
+            Some(Obj::Closure(closure)) => closure.function.to_string(),
+            _ => COLLECTED.to_string(),
+        };
+        match obj {
+            None => write!(f, "{}", COLLECTED),
+            Some(Obj::Closure(closure)) => write!(f, "{}", closure.function),
+            Some(Obj::Class(class)) => write!(f, "{}", class.name),
+            Some(Obj::Instance(instance)) => write!(f, "{} instance", class_name(instance.class)),
+            Some(Obj::BoundMethod(bound)) => write!(f, "{}", function(bound.method)),
+            Some(Obj::Upvalue(_)) => write!(f, "<upvalue>"),
+        }
+    }
+}
+
+#[test]
+fn test_collect_frees_unreachable_cycles() {
+    let mut heap = Heap::new();
+    let class = heap.alloc(Obj::Class(Class::new("Node")));
+    let a = heap.alloc(Obj::Instance(Instance::new(class)));
+    let b = heap.alloc(Obj::Instance(Instance::new(class)));
+    heap.instance_mut(a).fields.set("next".into(), Value::Instance(b));
+    heap.instance_mut(b).fields.set("next".into(), Value::Instance(a));
+    assert_eq!(heap.len(), 3);
+
+    heap.mark(a);
+    heap.collect();
+    assert_eq!(heap.len(), 3);
+
+    heap.mark(class);
+    heap.collect();
+    assert_eq!(heap.len(), 1);
+    assert_eq!(heap.class(class).name, "Node");
+
+    // freed slots are reused, but old handles don't see the new object
+    let c = heap.alloc(Obj::Instance(Instance::new(class)));
+    assert!(c != a && c != b);
+    assert!(heap.get(a).is_none() && heap.get(b).is_none());
+    assert!(heap.contains(c));
+    assert_eq!(heap.display(&Value::Instance(c)).to_string(), "Node instance");
+    assert_eq!(heap.display(&Value::Instance(a)).to_string(), "<collected>");
+
+    // marking a stale handle doesn't keep the slot's new object alive
+    heap.mark(a);
+    heap.mark(b);
+    heap.mark(class);
+    heap.collect();
+    assert!(!heap.contains(c));
+}
@@ -0,0 +1,47 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Deduplicates strings so each distinct identifier or string constant is
+/// allocated once and shared by every chunk that uses it.
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Rc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Interner {
+            strings: HashSet::new(),
+        }
+    }
+
+    /// Returns the shared copy of `s`, allocating it the first time.
+    pub fn intern(&mut self, s: &str) -> Rc<str> {
+        if let Some(interned) = self.strings.get(s) {
+            return Rc::clone(interned);
+        }
+        let interned: Rc<str> = Rc::from(s);
+        self.strings.insert(Rc::clone(&interned));
+        interned
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+#[test]
+fn test_intern_shares_allocations() {
+    let mut strings = Interner::new();
+    let a = strings.intern("name");
+    let b = strings.intern(&String::from("name"));
+    let c = strings.intern("other");
+
+    assert!(Rc::ptr_eq(&a, &b));
+    assert!(!Rc::ptr_eq(&a, &c));
+    assert_eq!(strings.len(), 2);
+}
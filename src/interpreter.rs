@@ -0,0 +1,146 @@
+use crate::chunk::Chunk;
+use crate::error::LoxError;
+use crate::heap::{Displayed, Heap};
+use crate::value::Value;
+use crate::vm::{InterpretResult, VM};
+
+/// Entry point for embedding rlox. Owns a single `VM`, so globals defined by
+/// one call to `eval` are visible to the next.
+pub struct Interpreter {
+    vm: VM,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter { vm: VM::new() }
+    }
+
+    /// Compiles and runs `source`. If the script ends with an expression
+    /// statement its value is returned, otherwise `nil`.
+    ///
+    /// Objects in the result live on the VM's heap. Unless they're stored in
+    /// a global, they may be collected the next time code runs; after that
+    /// `Heap::get` returns `None` for them and they display as `<collected>`.
+    pub fn eval(&mut self, source: &str) -> Result<Value, LoxError> {
+        Self::result(self.vm.interpret(source))
+    }
+
+    pub fn compile(&mut self, source: &str) -> Result<Chunk, LoxError> {
+        self.vm.compile(source).map_err(LoxError::Compile)
+    }
+
+    pub fn run(&mut self, chunk: Chunk) -> Result<Value, LoxError> {
+        Self::result(self.vm.execute(chunk))
+    }
+
+    fn result(result: InterpretResult) -> Result<Value, LoxError> {
+        match result {
+            InterpretResult::InterpretOk(value) => Ok(value),
+            InterpretResult::CompileError(e) => Err(LoxError::Compile(e)),
+            InterpretResult::RuntimeError(e) => Err(LoxError::Runtime(e)),
+        }
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.vm.get_global(name)
+    }
+
+    /// Returns false if the global couldn't be defined, see
+    /// `VM::set_global`.
+    pub fn set_global(&mut self, name: &str, value: Value) -> bool {
+        self.vm.set_global(name, value)
+    }
+
+    /// Exposes a Rust function to scripts, see `VM::define_native`.
+    pub fn define_native<F>(&mut self, name: &str, arity: usize, function: F) -> bool
+    where
+        F: Fn(&Heap, &[Value]) -> Result<Value, String> + 'static,
+    {
+        self.vm.define_native(name, arity, function)
+    }
+
+    /// Formats a value returned by this interpreter.
+    pub fn display<'a>(&'a self, value: &'a Value) -> Displayed<'a> {
+        self.vm.display(value)
+    }
+
+    pub fn vm(&mut self) -> &mut VM {
+        &mut self.vm
+    }
+}
+
+#[test]
+fn test_eval_returns_trailing_expression() {
+    let mut interpreter = Interpreter::new();
+    assert!(matches!(interpreter.eval("1 + 2;"), Ok(Value::Number(n)) if n == 3.0));
+    assert!(matches!(interpreter.eval("var a = 1;"), Ok(Value::Nil)));
+    assert!(matches!(interpreter.eval("{ 1; }"), Ok(Value::Nil)));
+}
+
+#[test]
+fn test_globals_persist_between_evals() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("var greeting = \"hello\";").unwrap();
+    assert!(interpreter.set_global("name", Value::String("lox".into())));
+
+    let greeting = interpreter.eval("greeting + \" \" + name;").unwrap();
+    assert_eq!(interpreter.display(&greeting).to_string(), "hello lox");
+
+    interpreter.eval("greeting = \"bye\";").unwrap();
+    assert!(matches!(interpreter.get_global("greeting"), Some(Value::String(s)) if &*s == "bye"));
+}
+
+#[test]
+fn test_errors() {
+    let mut interpreter = Interpreter::new();
+    assert!(matches!(interpreter.eval("print undefined;"), Err(LoxError::Runtime(_))));
+    assert!(matches!(interpreter.eval("print 1 +;"), Err(LoxError::Compile(_))));
+}
+
+#[test]
+fn test_reports_every_compile_error() {
+    use crate::error::CompileErrorKind;
+
+    let source = "var = 2;\nprint (3;\nprint 4;\n1 = 2;\n{ var a = 1; var a = 2; }\nprint 1 +;";
+    let errors = match Interpreter::new().eval(source) {
+        Err(LoxError::Compile(errors)) => errors,
+        other => panic!("expected compile errors, got {:?}", other),
+    };
+
+    let found: Vec<(usize, CompileErrorKind)> = errors
+        .iter()
+        .map(|e| (e.span.line, e.kind))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (1, CompileErrorKind::UnexpectedToken),
+            (2, CompileErrorKind::UnexpectedToken),
+            (4, CompileErrorKind::InvalidAssignmentTarget),
+            (5, CompileErrorKind::DuplicateVariable),
+            (6, CompileErrorKind::ExpectedExpression),
+        ]
+    );
+    assert_eq!(errors[0].to_string(), "[line 1] Error at '=': Expect variable name.");
+    assert_eq!(errors[4].to_string(), "[line 6] Error at ';': Expect expression.");
+}
+
+#[test]
+fn test_errors_quote_the_source() {
+    let first_error = |source: &str| match Interpreter::new().eval(source) {
+        Err(LoxError::Compile(errors)) => errors[0].to_string(),
+        other => panic!("expected compile errors, got {:?}", other),
+    };
+    assert_eq!(first_error("print 1 3.0;"), "[line 1] Error at '3.0': Expect ';' after value.");
+    assert_eq!(first_error("print 0x1F;\nprint 1 0x1F;"), "[line 2] Error at '0x1F': Expect ';' after value.");
+    assert_eq!(
+        first_error("print \"a\\tb\" \"c\\nd\";"),
+        "[line 1] Error at '\"c\\nd\"': Expect ';' after value."
+    );
+}
@@ -0,0 +1,20 @@
+pub mod chunk;
+pub mod error;
+pub mod globals;
+pub mod heap;
+pub mod interner;
+pub mod interpreter;
+pub mod math;
+pub mod natives;
+pub mod object;
+pub mod op;
+pub mod parser;
+pub mod scanner;
+pub mod symtable;
+pub mod token;
+pub mod value;
+pub mod vm;
+
+pub use error::{LoxError, RuntimeError};
+pub use interpreter::Interpreter;
+pub use value::Value;
@@ -1,147 +1,98 @@
-mod chunk;
-mod op;
-mod parser;
-mod scanner;
-mod token;
-mod value;
-mod vm;
-mod symtable;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
 
-use chunk::Chunk;
-use op::Op;
-use scanner::Scanner;
-use value::Value;
-use vm::VM;
+use rlox::{Interpreter, LoxError};
 
-    // let line = r#"
-    //     print 11 + 22*33;
-    //     print 420*33;
-    //     print "something";
-    //     print "something" + " else";
-    //     print ("something" == "something else");
-    //     var lol = 17 + (42-17);
-    //     print lol;
-    //     var lmao = "lmao";
-    //     var ayy = "WTF";
-    //     ayy = "ayy";
-    //     print ayy + " " + lmao;
-    // "#;
+// exit codes follow the BSD sysexits.h convention, same as clox
+const EX_USAGE: u8 = 64;
+const EX_DATAERR: u8 = 65;
+const EX_SOFTWARE: u8 = 70;
+const EX_IOERR: u8 = 74;
 
-    // let line = r#"
-    //     var breakfast = "beignets";
-    //     var beverage = "cafe au lait";
-    //     breakfast = breakfast + " with " + beverage;
-
-    //     print breakfast;
-    // "#;
-
-    // let line = r#"
-    // {
-    //     var a = "lmao";
-    //     {
-    //         var a = 1111;
-    //         var b = a * 3;
-    //         print a;
-    //         print b;
-    //     }
-    //     print a;
-    // }
-    // "#;
-
-    // let line = r#"
-    // {
-    //     var a = "lmao";
-    //     {
-    //         var a = a;
-    //         a = "not lmao";
-    //         print a;
-    //     }
-    //     print a;
-    // }
-    // "#;
-
-    // let line = r#"
-    // {
-    //     var a = "lm" + "ao";
-    //     {
-    //         var a = "not " + a;
-    //         print a;
-    //     }
-    //     print a;
-    // }
-    // "#;
-
-    // let line = r#"
-    // {
-    //     var a = 0;
-    //     var should_print = 0;
+struct Options {
+    disassemble: bool,
+}
 
-    //     while (a < 1000000) {
-    //         a = a + 1;
-    //         should_print = should_print + 1;
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let mut opts = Options { disassemble: false };
+    args.retain(|arg| match arg.as_str() {
+        "--disassemble" => {
+            opts.disassemble = true;
+            false
+        }
+        _ => true,
+    });
+
+    match args.as_slice() {
+        [] => repl(&opts),
+        [path] => run_file(&opts, path),
+        _ => {
+            eprintln!("Usage: rlox [--disassemble] [path]");
+            ExitCode::from(EX_USAGE)
+        }
+    }
+}
 
-    //         if (should_print == 100000) {
-    //             should_print = 0;
-    //             print a;
-    //         }
-    //     }
-    // }
-    // "#;
+fn repl(opts: &Options) -> ExitCode {
+    let mut interpreter = Interpreter::new();
+    let stdin = io::stdin();
+    let mut line = String::new();
 
-fn main() {
-    let line = r#"
-    {
-        // god forgive me
-        var a = 0;
-        var fizzer = 0;
-        var buzzer = 0;
-        while (a < 30) {
-            a = a + 1;
-            fizzer = fizzer + 1;
-            buzzer = buzzer + 1;
+    loop {
+        print!("> ");
+        io::stdout().flush().ok();
 
-            if (fizzer != 3 and buzzer != 5) {
-                print a;
-            } else {
-                var msg = "";
-                if (fizzer == 3) {
-                    msg = msg + "Fizz";
-                    fizzer = 0;
-                } 
-                if (buzzer == 5) {
-                    msg = msg + "Buzz";
-                    buzzer = 0;
+        line.clear();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) => {
+                println!();
+                return ExitCode::SUCCESS;
+            }
+            Ok(_) => {
+                if let Err(e) = run(opts, &mut interpreter, &line) {
+                    report(&e);
                 }
-                print msg;
+            }
+            Err(e) => {
+                eprintln!("Error reading input: {}", e);
+                return ExitCode::from(EX_IOERR);
             }
         }
     }
-    "#;
-
-    println!("Line: '{}'", line);
-
-    let tokens = Scanner::new(line).scan_tokens();
-
-    println!("Tokens:");
-    tokens
-        .iter()
-        .enumerate()
-        .for_each(|(i, token)| println!("{:>2} -> {:?}", i, token));
-
-    let mut parser = parser::Parser::new(tokens);
-    parser.compile();
-
-    let mut chunk = Chunk::new();
-    parser
-        .ops
-        .into_iter()
-        .for_each(|op| chunk.write_chunk(op, 1));
+}
 
-    println!();
-    chunk.dissassemble_chunk("test chunk");
+fn run_file(opts: &Options, path: &str) -> ExitCode {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Could not read file '{}': {}", path, e);
+            return ExitCode::from(EX_IOERR);
+        }
+    };
+
+    let mut interpreter = Interpreter::new();
+    match run(opts, &mut interpreter, &source) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            report(&e);
+            match e {
+                LoxError::Compile(_) => ExitCode::from(EX_DATAERR),
+                LoxError::Runtime(_) => ExitCode::from(EX_SOFTWARE),
+            }
+        }
+    }
+}
 
-    let mut vm = VM::new();
-    vm.chunk = chunk;
-    vm.interpret();
+fn run(opts: &Options, interpreter: &mut Interpreter, source: &str) -> Result<(), LoxError> {
+    let chunk = interpreter.compile(source)?;
+    if opts.disassemble {
+        chunk.dissassemble_chunk("script");
+    }
+    interpreter.run(chunk)?;
+    Ok(())
 }
 
+fn report(e: &LoxError) {
+    eprintln!("{}", e);
+}
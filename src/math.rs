@@ -0,0 +1,129 @@
+//! Math built-ins: the usual functions on numbers, `PI` and `E`, and a
+//! deterministic pseudo-random number generator.
+
+use std::cell::Cell;
+use std::f64::consts;
+use std::rc::Rc;
+
+use crate::value::Value;
+use crate::vm::VM;
+
+/// The generator's seed until a script calls `seed_random`, so runs are
+/// reproducible by default.
+const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+pub(crate) fn define(vm: &mut VM) {
+    vm.set_global("PI", Value::Number(consts::PI));
+    vm.set_global("E", Value::Number(consts::E));
+
+    unary(vm, "sqrt", f64::sqrt);
+    unary(vm, "floor", f64::floor);
+    unary(vm, "ceil", f64::ceil);
+    unary(vm, "round", f64::round);
+    unary(vm, "abs", f64::abs);
+    unary(vm, "sin", f64::sin);
+    unary(vm, "cos", f64::cos);
+    unary(vm, "tan", f64::tan);
+    unary(vm, "log", f64::ln);
+    unary(vm, "exp", f64::exp);
+    binary(vm, "pow", f64::powf);
+    binary(vm, "min", f64::min);
+    binary(vm, "max", f64::max);
+
+    let rng = Rc::new(Rng::new(DEFAULT_SEED));
+    let seeded = Rc::clone(&rng);
+    vm.define_native("seed_random", 1, move |_, args| match args {
+        [Value::Number(seed)] => {
+            seeded.seed(seed.to_bits());
+            Ok(Value::Nil)
+        }
+        _ => Err("Argument must be a number.".to_string()),
+    });
+    let random = Rc::clone(&rng);
+    vm.define_native("random", 0, move |_, _| Ok(Value::Number(random.next_f64())));
+    vm.define_native("random_int", 2, move |_, args| match args {
+        [Value::Number(min), Value::Number(max)] => {
+            if min.fract() != 0.0 || max.fract() != 0.0 {
+                return Err("Arguments must be integers.".to_string());
+            }
+            if min > max {
+                return Err("Minimum must not be greater than maximum.".to_string());
+            }
+            let range = ((max - min) as u64).saturating_add(1);
+            Ok(Value::Number(min + rng.below(range) as f64))
+        }
+        _ => Err("Arguments must be numbers.".to_string()),
+    });
+}
+
+/// Defines a native applying `f` to one number.
+fn unary(vm: &mut VM, name: &str, f: fn(f64) -> f64) {
+    vm.define_native(name, 1, move |_, args| match args {
+        [Value::Number(n)] => Ok(Value::Number(f(*n))),
+        _ => Err("Argument must be a number.".to_string()),
+    });
+}
+
+/// Defines a native applying `f` to two numbers.
+fn binary(vm: &mut VM, name: &str, f: fn(f64, f64) -> f64) {
+    vm.define_native(name, 2, move |_, args| match args {
+        [Value::Number(a), Value::Number(b)] => Ok(Value::Number(f(*a, *b))),
+        _ => Err("Arguments must be numbers.".to_string()),
+    });
+}
+
+/// xorshift64* generator. Not suitable for anything security related.
+struct Rng {
+    state: Cell<u64>,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        let rng = Rng { state: Cell::new(0) };
+        rng.seed(seed);
+        rng
+    }
+
+    /// Restarts the sequence. Seeds go through splitmix64 first, so that
+    /// similar seeds give unrelated sequences and the state is never zero.
+    fn seed(&self, seed: u64) {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        self.state.set(if z == 0 { DEFAULT_SEED } else { z });
+    }
+
+    fn next(&self) -> u64 {
+        let mut x = self.state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `0..range`.
+    fn below(&self, range: u64) -> u64 {
+        ((self.next() as u128 * range as u128) >> 64) as u64
+    }
+}
+
+#[test]
+fn test_rng_is_deterministic_and_in_range() {
+    let a = Rng::new(42);
+    let b = Rng::new(42);
+    for _ in 0..1000 {
+        let x = a.next_f64();
+        assert_eq!(x, b.next_f64());
+        assert!((0.0..1.0).contains(&x));
+        assert!(a.below(6) < 6);
+        b.below(6);
+    }
+    assert_ne!(Rng::new(1).next(), Rng::new(2).next());
+}
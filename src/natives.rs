@@ -0,0 +1,61 @@
+//! Built-in functions every script can call.
+
+use std::time::Instant;
+
+use crate::heap::Heap;
+use crate::math;
+use crate::value::Value;
+use crate::vm::VM;
+
+pub(crate) fn define(vm: &mut VM) {
+    let start = Instant::now();
+    vm.define_native("clock", 0, move |_, _| Ok(Value::Number(start.elapsed().as_secs_f64())));
+    vm.define_native("str", 1, str);
+    vm.define_native("num", 1, num);
+    vm.define_native("len", 1, len);
+    vm.define_native("type", 1, type_of);
+    math::define(vm);
+}
+
+/// `str(value)`: the value as `print` would show it.
+fn str(heap: &Heap, args: &[Value]) -> Result<Value, String> {
+    match &args[0] {
+        Value::String(s) => Ok(Value::String(s.clone())),
+        value => Ok(Value::String(heap.display(value).to_string().into())),
+    }
+}
+
+/// `num(value)`: parses a string as a number, or returns `nil` if it isn't
+/// one. Numbers are returned as they are.
+fn num(_: &Heap, args: &[Value]) -> Result<Value, String> {
+    match &args[0] {
+        Value::Number(n) => Ok(Value::Number(*n)),
+        Value::String(s) => Ok(s.trim().parse().map_or(Value::Nil, Value::Number)),
+        _ => Err("Argument must be a number or a string.".to_string()),
+    }
+}
+
+/// `len(string)`: the number of characters in the string.
+fn len(_: &Heap, args: &[Value]) -> Result<Value, String> {
+    match &args[0] {
+        Value::String(s) => Ok(Value::Number(s.chars().count() as f64)),
+        _ => Err("Argument must be a string.".to_string()),
+    }
+}
+
+/// `type(value)`: the name of the value's type.
+fn type_of(_: &Heap, args: &[Value]) -> Result<Value, String> {
+    let name = match &args[0] {
+        Value::Number(_) => "number",
+        Value::Bool(_) => "bool",
+        Value::String(_) => "string",
+        Value::Function(_)
+        | Value::NativeFn(_)
+        | Value::Closure(_)
+        | Value::BoundMethod(_) => "function",
+        Value::Class(_) => "class",
+        Value::Instance(_) => "instance",
+        Value::Nil => "nil",
+    };
+    Ok(Value::String(name.into()))
+}
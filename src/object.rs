@@ -0,0 +1,114 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+use crate::chunk::Chunk;
+use crate::heap::{Handle, Heap};
+use crate::symtable::SymTable;
+use crate::value::Value;
+
+/// Compiled code. Functions are immutable once compiled and never refer to
+/// runtime objects, so they're reference counted rather than living on the
+/// `Heap`.
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    pub chunk: Chunk,
+    pub upvalue_count: usize,
+}
+
+impl Function {
+    pub fn new(name: &str) -> Function {
+        Function {
+            name: name.to_string(),
+            arity: 0,
+            chunk: Chunk::new(),
+            upvalue_count: 0,
+        }
+    }
+}
+
+/// The Rust side of a native function: gets the heap, to look at objects
+/// the arguments refer to, and exactly `arity` arguments. An `Err` becomes a
+/// runtime error with that message.
+pub type NativeFunction = dyn Fn(&Heap, &[Value]) -> Result<Value, String>;
+
+/// A function implemented in Rust, registered with `VM::define_native`.
+pub struct NativeFn {
+    pub name: String,
+    pub arity: usize,
+    pub function: Box<NativeFunction>,
+}
+
+impl Debug for NativeFn {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.debug_struct("NativeFn")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    /// Handles to `Obj::Upvalue`s.
+    pub upvalues: Vec<Handle>,
+}
+
+/// A captured variable. It points into the VM stack while the variable is
+/// still in scope and takes ownership of the value once it is closed.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self.name.as_str() {
+            "" => write!(f, "<script>"),
+            name => write!(f, "<fn {}>", name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    pub methods: SymTable,
+}
+
+impl Class {
+    pub fn new(name: &str) -> Class {
+        Class {
+            name: name.to_string(),
+            methods: SymTable::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    /// Handle to an `Obj::Class`.
+    pub class: Handle,
+    pub fields: SymTable,
+}
+
+impl Instance {
+    pub fn new(class: Handle) -> Instance {
+        Instance {
+            class,
+            fields: SymTable::new(),
+        }
+    }
+}
+
+/// A method closure together with the instance it was accessed on, so that
+/// `this` is bound when it's eventually called.
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Value,
+    /// Handle to an `Obj::Closure`.
+    pub method: Handle,
+}
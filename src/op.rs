@@ -1,64 +1,170 @@
 use std::fmt::{Display, Formatter};
 
-use crate::value::Value;
-
-#[derive(Debug)]
+/// A bytecode instruction. Each opcode is a single byte in `Chunk::code`,
+/// followed by its operands; multi-byte operands are big-endian. Jump
+/// offsets are relative to the end of the instruction.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Op {
-    SetGlobal(String),
-    GetGlobal(String),
-    SetLocal(usize),
-    GetLocal(usize),
-    DefineGlobal(String),
-    Constant(Value),
+    /// Operand: u8 index into the constant pool.
+    Constant,
+    /// Operand: u24 index into the constant pool.
+    ConstantLong,
+    Nil,
+    True,
+    False,
     Pop,
-
-    Add,
-    Subtract,
-    Divide,
-    Multiply,
-
-
-    JumpIfFalse(usize),
-    JumpIfTrue(usize),
-    Jump(usize),
-    Loop(usize),
-
+    /// Operand: u8 stack slot.
+    GetLocal,
+    /// Operand: u8 stack slot.
+    SetLocal,
+    /// Operand: u16 global slot.
+    GetGlobal,
+    /// Operand: u16 global slot.
+    DefineGlobal,
+    /// Operand: u16 global slot.
+    SetGlobal,
+    /// Operand: u8 upvalue index.
+    GetUpvalue,
+    /// Operand: u8 upvalue index.
+    SetUpvalue,
+    /// Operand: u16 name constant.
+    GetProperty,
+    /// Operand: u16 name constant.
+    SetProperty,
+    /// Operand: u16 name constant.
+    GetSuper,
     Equal,
     Greater,
     Less,
-    Negate,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
     Not,
+    Negate,
+    Stringify,
     Print,
-    Nop,
+    /// Operand: u16 forward offset.
+    Jump,
+    /// Operand: u16 forward offset.
+    JumpIfFalse,
+    /// Operand: u16 forward offset.
+    JumpIfTrue,
+    /// Operand: u16 backward offset.
+    Loop,
+    /// Operand: u8 argument count.
+    Call,
+    /// Operands: u16 function constant, then an (is_local, index) byte pair per upvalue.
+    Closure,
+    CloseUpvalue,
     Return,
+    /// Operand: u16 name constant.
+    Class,
+    Inherit,
+    /// Operand: u16 name constant.
+    Method,
+}
+
+impl Op {
+    /// Every opcode, indexed by its byte value.
+    const ALL: [Op; 38] = [
+        Op::Constant,
+        Op::ConstantLong,
+        Op::Nil,
+        Op::True,
+        Op::False,
+        Op::Pop,
+        Op::GetLocal,
+        Op::SetLocal,
+        Op::GetGlobal,
+        Op::DefineGlobal,
+        Op::SetGlobal,
+        Op::GetUpvalue,
+        Op::SetUpvalue,
+        Op::GetProperty,
+        Op::SetProperty,
+        Op::GetSuper,
+        Op::Equal,
+        Op::Greater,
+        Op::Less,
+        Op::Add,
+        Op::Subtract,
+        Op::Multiply,
+        Op::Divide,
+        Op::Not,
+        Op::Negate,
+        Op::Stringify,
+        Op::Print,
+        Op::Jump,
+        Op::JumpIfFalse,
+        Op::JumpIfTrue,
+        Op::Loop,
+        Op::Call,
+        Op::Closure,
+        Op::CloseUpvalue,
+        Op::Return,
+        Op::Class,
+        Op::Inherit,
+        Op::Method,
+    ];
+
+    pub fn from_byte(byte: u8) -> Option<Op> {
+        Op::ALL.get(byte as usize).copied()
+    }
 }
 
 impl Display for Op {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        match self {
-            Op::Add => write!(f, "{:>20} |", "OP_ADD"),
-            Op::Constant(v) => write!(f, "{:>20} | {:>4}", "OP_CONSTANT", v),
-            Op::SetGlobal(s) => write!(f, "{:>20} | {:?}", "OP_SET_GLOBAL", s),
-            Op::GetGlobal(s) => write!(f, "{:>20} | {:?}", "OP_GET_GLOBAL", s),
-            Op::SetLocal(s) => write!(f, "{:>20} | {:?}", "OP_SET_LOCAL", s),
-            Op::GetLocal(s) => write!(f, "{:>20} | {:?}", "OP_GET_LOCAL", s),
-            Op::DefineGlobal(s) => write!(f, "{:>20} | {:?}", "OP_DEFINE_GLOBAL", s),
-            Op::JumpIfFalse(i) => write!(f, "{:>20} | {:?}", "OP_JUMP_IF_FALSE", i),
-            Op::JumpIfTrue(i) => write!(f, "{:>20} | {:?}", "OP_JUMP_IF_TRUE", i),
-            Op::Jump(i) => write!(f, "{:>20} | {:?}", "OP_JUMP", i),
-            Op::Loop(i) => write!(f, "{:>20} | {:?}", "OP_LOOP", i),
-            Op::Divide => write!(f, "{:>20} |", "OP_DIVIDE"),
-            Op::Equal => write!(f, "{:>20} |", "OP_EQUAL"),
-            Op::Greater => write!(f, "{:>20} |", "OP_GREATER"),
-            Op::Less => write!(f, "{:>20} |", "OP_LESS"),
-            Op::Multiply => write!(f, "{:>20} |", "OP_MULTIPLY"),
-            Op::Negate => write!(f, "{:>20} |", "OP_NEGATE"),
-            Op::Nop => write!(f, "{:>20} |", "NOp"),
-            Op::Not => write!(f, "{:>20} |", "OP_NOT"),
-            Op::Pop => write!(f, "{:>20} |", "OP_POP"),
-            Op::Print => write!(f, "{:>20} |", "OP_PRINT"),
-            Op::Return => write!(f, "{:>20} |", "OP_RETURN"),
-            Op::Subtract => write!(f, "{:>20} |", "OP_SUBTRACT"),
-        }
+        let name = match self {
+            Op::Constant => "OP_CONSTANT",
+            Op::ConstantLong => "OP_CONSTANT_LONG",
+            Op::Nil => "OP_NIL",
+            Op::True => "OP_TRUE",
+            Op::False => "OP_FALSE",
+            Op::Pop => "OP_POP",
+            Op::GetLocal => "OP_GET_LOCAL",
+            Op::SetLocal => "OP_SET_LOCAL",
+            Op::GetGlobal => "OP_GET_GLOBAL",
+            Op::DefineGlobal => "OP_DEFINE_GLOBAL",
+            Op::SetGlobal => "OP_SET_GLOBAL",
+            Op::GetUpvalue => "OP_GET_UPVALUE",
+            Op::SetUpvalue => "OP_SET_UPVALUE",
+            Op::GetProperty => "OP_GET_PROPERTY",
+            Op::SetProperty => "OP_SET_PROPERTY",
+            Op::GetSuper => "OP_GET_SUPER",
+            Op::Equal => "OP_EQUAL",
+            Op::Greater => "OP_GREATER",
+            Op::Less => "OP_LESS",
+            Op::Add => "OP_ADD",
+            Op::Subtract => "OP_SUBTRACT",
+            Op::Multiply => "OP_MULTIPLY",
+            Op::Divide => "OP_DIVIDE",
+            Op::Not => "OP_NOT",
+            Op::Negate => "OP_NEGATE",
+            Op::Stringify => "OP_STRINGIFY",
+            Op::Print => "OP_PRINT",
+            Op::Jump => "OP_JUMP",
+            Op::JumpIfFalse => "OP_JUMP_IF_FALSE",
+            Op::JumpIfTrue => "OP_JUMP_IF_TRUE",
+            Op::Loop => "OP_LOOP",
+            Op::Call => "OP_CALL",
+            Op::Closure => "OP_CLOSURE",
+            Op::CloseUpvalue => "OP_CLOSE_UPVALUE",
+            Op::Return => "OP_RETURN",
+            Op::Class => "OP_CLASS",
+            Op::Inherit => "OP_INHERIT",
+            Op::Method => "OP_METHOD",
+        };
+        f.pad(name)
+    }
+}
+
+#[test]
+fn test_opcodes_round_trip() {
+    for (i, op) in Op::ALL.iter().enumerate() {
+        assert_eq!(*op as u8 as usize, i);
+        assert_eq!(Op::from_byte(*op as u8), Some(*op));
     }
+    assert_eq!(Op::from_byte(Op::ALL.len() as u8), None);
 }
@@ -1,144 +1,767 @@
-use crate::token::TokenType;
+use crate::chunk::{Chunk, Doc, DocTarget};
+use crate::error::{CompileError, CompileErrorKind, ErrorAt};
+use crate::globals::Globals;
+use crate::interner::Interner;
+use crate::object::Function;
+use crate::op::Op;
+use crate::scanner::Scanner;
+use crate::token::{Span, Token, TokenType};
 use crate::value::Value;
-use crate::Op;
 
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::rc::Rc;
 
-pub struct Parser {
-    tokens: Vec<TokenType>,
-    prev: usize,
-    current: usize,
-    scope_depth: i32,
-    local_count: usize,
+const MAX_ARGS: usize = 255;
+const MAX_UPVALUES: usize = 256;
+const MAX_LOCALS: usize = 256;
+/// How deep statements, blocks and expressions may nest. The parser
+/// recurses once per level, so this keeps it well inside the stack.
+const MAX_NESTING: usize = 256;
+/// Depth of a local whose initializer is still being compiled.
+const UNINITIALIZED: i32 = -1;
+
+pub struct Parser<'src, 'ctx> {
+    scanner: Scanner<'src>,
+    /// Global slots and interned strings of the VM the code will run on.
+    globals: &'ctx mut Globals,
+    strings: &'ctx mut Interner,
+    /// Shared by the chunks of every compiled function.
+    source: Rc<str>,
+    prev: Token<'src>,
+    current: Token<'src>,
+    compilers: Vec<Compiler>,
+    classes: Vec<ClassCompiler>,
+    errors: Vec<CompileError>,
+    panic_mode: bool,
+    /// Current depth of nested statements, blocks and expressions.
+    nesting: usize,
+    /// Set once `MAX_NESTING` is exceeded; the rest of the input is skipped
+    /// and no further errors are reported.
+    too_deep: bool,
+    last_expression_pop: Option<usize>,
+    /// Doc comments between the previous token and the current one.
+    doc: Vec<&'src str>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum FunctionKind {
+    Script,
+    Function,
+    Method,
+    Initializer,
+}
+
+/// Per-function compilation state. The parser keeps a stack of these, one
+/// for each function declaration it is currently nested in.
+struct Compiler {
+    function: Function,
+    kind: FunctionKind,
     locals: Vec<Local>,
-    pub ops: Vec<Op>,
+    upvalues: Vec<UpvalueRef>,
+    scope_depth: i32,
+    loops: Vec<LoopContext>,
+    /// Constant pool indices of the names used so far, so each name is
+    /// stored once per function.
+    names: HashMap<String, u16>,
+}
+
+/// Where a closure finds a captured variable when it is created: either a
+/// local slot of the enclosing function or one of the enclosing closure's
+/// own upvalues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct UpvalueRef {
+    index: usize,
+    is_local: bool,
+}
+
+impl Compiler {
+    fn new(kind: FunctionKind, name: &str) -> Self {
+        Compiler {
+            function: Function::new(name),
+            kind,
+            // slot 0 holds the function being called, or the receiver
+            // for methods
+            locals: vec![Local {
+                name: match kind {
+                    FunctionKind::Method | FunctionKind::Initializer => "this".to_string(),
+                    _ => String::new(),
+                },
+                depth: 0,
+                is_captured: false,
+            }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+            loops: Vec::new(),
+            names: HashMap::new(),
+        }
+    }
+}
+
+/// The innermost enclosing loop, used to compile `break` and `continue`.
+struct LoopContext {
+    /// Where `continue` jumps back to.
+    start: usize,
+    /// Scope depth outside the loop body; locals deeper than this are
+    /// discarded when jumping out of an iteration.
+    scope_depth: i32,
+    /// Forward jumps emitted by `break`, patched once the loop ends.
+    breaks: Vec<usize>,
+}
+
+/// Tracks the class declaration being compiled so uses of `this` and
+/// `super` can be checked.
+struct ClassCompiler {
+    name: String,
+    has_superclass: bool,
 }
 
 #[derive(Debug)]
 struct Local {
     name: String,
     depth: i32,
+    is_captured: bool,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<TokenType>) -> Self {
+impl<'src, 'ctx> Parser<'src, 'ctx> {
+    pub fn new(mut scanner: Scanner<'src>, globals: &'ctx mut Globals, strings: &'ctx mut Interner) -> Self {
+        let current = scanner.next().expect("the scanner always ends with Eof");
         Parser {
-            tokens,
-            prev: 0,
-            current: 0,
-            scope_depth: 0,
-            local_count: 0,
-            locals: Vec::new(),
-            ops: Vec::new(),
+            source: Rc::from(scanner.source()),
+            scanner,
+            globals,
+            strings,
+            prev: current.clone(),
+            current,
+            compilers: vec![Compiler::new(FunctionKind::Script, "")],
+            classes: Vec::new(),
+            errors: Vec::new(),
+            panic_mode: false,
+            nesting: 0,
+            too_deep: false,
+            last_expression_pop: None,
+            doc: Vec::new(),
         }
     }
 
-    pub fn compile(&mut self) {
+    fn compiler(&self) -> &Compiler {
+        self.compilers.last().expect("no function being compiled")
+    }
+
+    fn compiler_mut(&mut self) -> &mut Compiler {
+        self.compilers.last_mut().expect("no function being compiled")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.compiler_mut().function.chunk
+    }
+
+    fn code_len(&self) -> usize {
+        self.compiler().function.chunk.code.len()
+    }
+
+    fn scope_depth(&self) -> i32 {
+        self.compiler().scope_depth
+    }
+
+    pub fn compile(mut self) -> Result<Chunk, Vec<CompileError>> {
+        self.skip_trivia();
         loop {
             match self.current() {
                 TokenType::Eof => break,
                 _ => self.declaration(),
             }
         }
+
+        // a trailing top-level expression statement keeps its value on the
+        // stack so it becomes the result of the script
+        let code_len = self.code_len();
+        match self.last_expression_pop {
+            Some(i) if i + 1 == code_len => {
+                self.chunk().code[i] = Op::Return as u8;
+            }
+            _ => self.emit_return(),
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+        let script = self.compilers.pop().expect("no function being compiled");
+        let mut chunk = script.function.chunk;
+        chunk.source = self.source;
+        Ok(chunk)
     }
 
-    pub fn consume(&mut self, tt: TokenType) {
+    pub fn consume(&mut self, tt: TokenType, msg: &str) {
         if std::mem::discriminant(self.current()) == std::mem::discriminant(&tt) {
             self.advance();
             return;
         }
-        println!("Error: Expected {:?}, found {:?}", tt, self.current());
+        self.error_at_current(CompileErrorKind::UnexpectedToken, msg);
+    }
+
+    fn error(&mut self, kind: CompileErrorKind, msg: &str) {
+        let token = self.prev.clone();
+        self.error_at(&token, kind, msg);
+    }
+
+    fn error_at_current(&mut self, kind: CompileErrorKind, msg: &str) {
+        let token = self.current.clone();
+        self.error_at(&token, kind, msg);
+    }
+
+    fn error_at(&mut self, token: &Token, kind: CompileErrorKind, msg: &str) {
+        // while panicking, errors are cascades of the first one
+        if self.panic_mode || self.too_deep {
+            return;
+        }
+        self.panic_mode = true;
+
+        let at = match &token.kind {
+            TokenType::Eof => ErrorAt::End,
+            TokenType::Error(..) => ErrorAt::Nowhere,
+            // quote the token as written, not as the scanner normalized it
+            kind => match self.source.get(token.span.start..token.span.end) {
+                Some(lexeme) => ErrorAt::Token(lexeme.to_string()),
+                None => ErrorAt::Token(kind.to_string()),
+            },
+        };
+        self.errors.push(CompileError {
+            kind,
+            message: msg.to_string(),
+            span: token.span,
+            at,
+        });
+    }
+
+    /// Skips tokens until a statement boundary so that a single mistake
+    /// doesn't produce a cascade of errors.
+    fn synchronize(&mut self) {
+        self.panic_mode = false;
+
+        while !matches!(self.current(), TokenType::Eof) {
+            if let TokenType::Semicolon = self.prev() {
+                return;
+            }
+            match self.current() {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => self.advance(),
+            }
+        }
+    }
+
+    /// Goes one level deeper into the input, or reports an error if that
+    /// would exceed `MAX_NESTING`. Input nested that deep isn't worth
+    /// recovering from, so on error everything up to the end is skipped.
+    fn enter_nesting(&mut self) -> bool {
+        if self.nesting == MAX_NESTING {
+            self.error_at_current(CompileErrorKind::TooMuchNesting, "Too much nesting.");
+            self.too_deep = true;
+            while !matches!(self.current(), TokenType::Eof) {
+                self.advance();
+            }
+            return false;
+        }
+        self.nesting += 1;
+        true
+    }
+
+    fn leave_nesting(&mut self) {
+        self.nesting -= 1;
+    }
+
+    fn emit(&mut self, op: Op) {
+        let span = self.prev.span;
+        self.chunk().write_op(op, span);
+    }
+
+    /// Emits `op` for the code at `span` rather than the previous token, so
+    /// runtime errors point at e.g. the operator instead of its last operand.
+    fn emit_at(&mut self, op: Op, span: Span) {
+        self.chunk().write_op(op, span);
+    }
+
+    /// Emits an operand byte. Operands share their instruction's span.
+    fn emit_byte(&mut self, byte: u8) {
+        let chunk = self.chunk();
+        let span = chunk.span(chunk.code.len().saturating_sub(1));
+        chunk.write_byte(byte, span);
+    }
+
+    fn emit_u16(&mut self, value: u16) {
+        let [high, low] = value.to_be_bytes();
+        self.emit_byte(high);
+        self.emit_byte(low);
+    }
+
+    fn emit_constant(&mut self, value: Value) {
+        let index = self.chunk().add_constant(value);
+        if let Ok(index) = u8::try_from(index) {
+            self.emit(Op::Constant);
+            self.emit_byte(index);
+        } else if index < 1 << 24 {
+            let [_, high, mid, low] = (index as u32).to_be_bytes();
+            self.emit(Op::ConstantLong);
+            self.emit_byte(high);
+            self.emit_byte(mid);
+            self.emit_byte(low);
+        } else {
+            self.error(CompileErrorKind::TooManyConstants, "Too many constants in one chunk.");
+        }
+    }
+
+    /// Adds `value` to the constant pool for an instruction with a u16
+    /// operand.
+    fn make_constant(&mut self, value: Value) -> u16 {
+        let index = self.chunk().add_constant(value);
+        u16::try_from(index).unwrap_or_else(|_| {
+            self.error(CompileErrorKind::TooManyConstants, "Too many constants in one chunk.");
+            0
+        })
+    }
+
+    fn name_constant(&mut self, name: &str) -> u16 {
+        if let Some(index) = self.compiler().names.get(name) {
+            return *index;
+        }
+        let interned = self.strings.intern(name);
+        let index = self.make_constant(Value::String(interned));
+        self.compiler_mut().names.insert(name.to_string(), index);
+        index
+    }
+
+    /// Interns `s` as a string constant.
+    fn emit_string(&mut self, s: &str) {
+        let interned = self.strings.intern(s);
+        self.emit_constant(Value::String(interned));
+    }
+
+    /// The slot of the global variable `name`.
+    fn global_slot(&mut self, name: &str) -> u16 {
+        let name = self.strings.intern(name);
+        match self.globals.resolve(&name) {
+            Some(slot) => {
+                self.chunk().global_names.insert(slot, name);
+                slot
+            }
+            None => {
+                self.error(CompileErrorKind::TooManyGlobals, "Too many global variables.");
+                0
+            }
+        }
+    }
+
+    /// Emits `DefineGlobal` for the global `name`.
+    fn define_global(&mut self, name: &str) {
+        let slot = self.global_slot(name);
+        self.emit(Op::DefineGlobal);
+        self.emit_u16(slot);
+    }
+
+    /// Emits `op` with the name constant for `name` as its operand.
+    fn emit_name(&mut self, op: Op, name: &str) {
+        let index = self.name_constant(name);
+        self.emit(op);
+        self.emit_u16(index);
+    }
+
+    /// Emits a forward jump with a placeholder offset and returns the
+    /// offset's position so it can be fixed up with `patch_jump` once the
+    /// target is known.
+    fn emit_jump(&mut self, op: Op) -> usize {
+        self.emit(op);
+        self.emit_u16(u16::MAX);
+        self.code_len() - 2
+    }
+
+    /// Points the jump whose offset is at `at` to the next instruction to
+    /// be emitted.
+    fn patch_jump(&mut self, at: usize) {
+        let Ok(jump) = u16::try_from(self.code_len() - at - 2) else {
+            self.error(CompileErrorKind::JumpTooLarge, "Too much code to jump over.");
+            return;
+        };
+        self.chunk().code[at..at + 2].copy_from_slice(&jump.to_be_bytes());
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) {
+        self.emit(Op::Loop);
+        // the offset is taken from after the operand
+        let Ok(offset) = u16::try_from(self.code_len() + 2 - loop_start) else {
+            self.error(CompileErrorKind::JumpTooLarge, "Loop body too large.");
+            self.emit_u16(0);
+            return;
+        };
+        self.emit_u16(offset);
+    }
+
+    fn emit_return(&mut self) {
+        // initializers always return the instance
+        if self.compiler().kind == FunctionKind::Initializer {
+            self.emit(Op::GetLocal);
+            self.emit_byte(0);
+        } else {
+            self.emit(Op::Nil);
+        }
+        self.emit(Op::Return);
+    }
+
+    fn current(&self) -> &TokenType<'src> {
+        &self.current.kind
     }
 
-    fn current(&self) -> &TokenType {
-        &self.tokens[self.current]
+    fn prev(&self) -> &TokenType<'src> {
+        &self.prev.kind
     }
 
-    fn prev(&self) -> &TokenType {
-        &self.tokens[self.prev]
+    /// Pulls the next token from the scanner. Once it's exhausted the `Eof`
+    /// token is repeated.
+    fn next_token(&mut self) -> Token<'src> {
+        self.scanner.next().unwrap_or_else(|| self.current.clone())
     }
 
     fn advance(&mut self) {
-        self.prev = self.current;
-        self.current += 1;
+        let next = self.next_token();
+        self.prev = std::mem::replace(&mut self.current, next);
+        self.doc.clear();
+        self.skip_trivia();
+    }
+
+    /// Reports the errors the scanner left in the token stream and moves
+    /// past them, collecting doc comments for the next declaration.
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.current() {
+                TokenType::Error(kind, msg) => {
+                    let (kind, msg) = (*kind, msg.clone());
+                    self.error_at_current(kind, &msg);
+                }
+                TokenType::DocComment(text) => {
+                    let text = *text;
+                    self.doc.push(text);
+                }
+                _ => break,
+            }
+            self.current = self.next_token();
+        }
+    }
+
+    /// Takes the doc comments written directly before the current token.
+    fn take_doc(&mut self) -> Option<String> {
+        if self.doc.is_empty() {
+            return None;
+        }
+        let doc = self.doc.join("\n");
+        self.doc.clear();
+        Some(doc)
+    }
+
+    /// Records `doc` against the declaration whose name was just consumed.
+    fn document(&mut self, target: DocTarget, name: String, doc: Option<String>) {
+        if let Some(text) = doc {
+            let line = self.prev.span.line;
+            self.chunk().docs.push(Doc { target, name, text, line });
+        }
     }
 
     fn parse_precedence(&mut self, precedence: Precedence) {
-        self.advance();
-        let prefix_rule = parse_rules(self.prev()).prefix;
-        if prefix_rule.is_none() {
-            println!("Error: Expected expression");
+        if !self.enter_nesting() {
             return;
         }
+        self.advance();
+        let Some(prefix_rule) = parse_rules(self.prev()).prefix else {
+            self.error(CompileErrorKind::ExpectedExpression, "Expect expression.");
+            self.leave_nesting();
+            return;
+        };
 
-        let prefix_rule = prefix_rule.unwrap();
         let can_assign = precedence <= Precedence::Assignment;
         prefix_rule.parse(self, can_assign);
         while precedence <= parse_rules(self.current()).precedence {
             self.advance();
-            let infix_rule = parse_rules(self.prev()).infix.unwrap();
-            infix_rule.parse(self, can_assign)
+            if let Some(infix_rule) = parse_rules(self.prev()).infix {
+                infix_rule.parse(self, can_assign);
+            }
         }
 
         if can_assign {
             if let TokenType::Equal = self.current() {
-                println!("Error: Invalid assignment target.");
+                self.error_at_current(
+                    CompileErrorKind::InvalidAssignmentTarget,
+                    "Invalid assignment target.",
+                );
             }
         }
+        self.leave_nesting();
     }
 
     fn declaration(&mut self) {
+        let doc = self.take_doc();
         match self.current() {
             TokenType::Var => {
                 self.advance();
-                self.var_declaration();
+                self.var_declaration(doc);
+            },
+            TokenType::Fun => {
+                self.advance();
+                self.fun_declaration(doc);
+            },
+            TokenType::Class => {
+                self.advance();
+                self.class_declaration(doc);
             },
             _ => {
                 self.statement();
             },
         }
+
+        if self.panic_mode {
+            self.synchronize();
+        }
     }
 
-    fn var_declaration(&mut self) {
+    fn var_declaration(&mut self, doc: Option<String>) {
         match self.current() {
             TokenType::Identifier(iden) => {
-                let iden_str = iden.clone();
+                let iden_str = iden.to_string();
                 self.advance();
-                self.consume(TokenType::Equal);
-                self.expression();
-                self.consume(TokenType::Semicolon);
+                self.document(DocTarget::Variable, iden_str.clone(), doc);
+
+                // declared before the initializer so that reading it there
+                // can be reported
+                let local = self.scope_depth() > 0;
+                if local {
+                    self.push_local(iden_str.clone(), UNINITIALIZED);
+                }
+                if let TokenType::Equal = self.current() {
+                    self.advance();
+                    self.expression();
+                } else {
+                    self.emit(Op::Nil);
+                }
+                self.consume(TokenType::Semicolon, "Expect ';' after variable declaration.");
 
-                if self.scope_depth > 0 {
-                    self.add_local(iden_str);
+                if local {
+                    self.mark_initialized();
                     return;
                 }
 
-                self.ops.push(Op::DefineGlobal(iden_str));
+                self.define_global(&iden_str);
             },
             _ => {
-                println!("Error: Expected identifier");
+                self.error_at_current(CompileErrorKind::UnexpectedToken, "Expect variable name.");
             },
         }
     }
 
-    fn add_local(&mut self, iden: String) {
-        for local in self.locals.iter().rev() {
-            if local.depth < self.scope_depth {
-                break;
+    fn fun_declaration(&mut self, doc: Option<String>) {
+        let TokenType::Identifier(iden) = self.current() else {
+            self.error_at_current(CompileErrorKind::UnexpectedToken, "Expect function name.");
+            return;
+        };
+        let iden_str = iden.to_string();
+        self.advance();
+        self.document(DocTarget::Function, iden_str.clone(), doc);
+
+        // the local is declared before the body so the function can
+        // refer to itself recursively
+        if self.scope_depth() > 0 {
+            self.add_local(iden_str.clone());
+        }
+        self.function(FunctionKind::Function, &iden_str);
+        if self.scope_depth() == 0 {
+            self.define_global(&iden_str);
+        }
+    }
+
+    fn class_declaration(&mut self, doc: Option<String>) {
+        let TokenType::Identifier(iden) = self.current() else {
+            self.error_at_current(CompileErrorKind::UnexpectedToken, "Expect class name.");
+            return;
+        };
+        let class_name = iden.to_string();
+        self.advance();
+        self.document(DocTarget::Class, class_name.clone(), doc);
+
+        self.emit_name(Op::Class, &class_name);
+        if self.scope_depth() > 0 {
+            self.add_local(class_name.clone());
+        } else {
+            self.define_global(&class_name);
+        }
+        self.classes.push(ClassCompiler {
+            name: class_name.clone(),
+            has_superclass: false,
+        });
+
+        if let TokenType::Less = self.current() {
+            self.advance();
+            let TokenType::Identifier(iden) = self.current() else {
+                self.error_at_current(CompileErrorKind::UnexpectedToken, "Expect superclass name.");
+                self.classes.pop();
+                return;
+            };
+            let superclass_name = iden.to_string();
+            self.advance();
+            if superclass_name == class_name {
+                self.error(
+                    CompileErrorKind::InvalidSuperclass,
+                    "A class can't inherit from itself.",
+                );
             }
-            if local.name == iden {
-                println!("Error: Already variable with this name in this scope.");
+            self.named_variable(superclass_name, false);
+
+            // methods capture the superclass through a "super" local in a
+            // scope wrapping the class body
+            self.begin_scope();
+            self.add_local("super".to_string());
+
+            self.named_variable(class_name.clone(), false);
+            self.emit(Op::Inherit);
+            if let Some(class) = self.classes.last_mut() {
+                class.has_superclass = true;
+            }
+        }
+
+        // methods are attached to the class sitting on top of the stack
+        self.named_variable(class_name, false);
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.");
+        while !matches!(self.current(), TokenType::RightBrace | TokenType::Eof) {
+            self.method();
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.");
+        self.emit(Op::Pop);
+
+        if self.classes.pop().is_some_and(|class| class.has_superclass) {
+            self.end_scope();
+        }
+    }
+
+    fn method(&mut self) {
+        let doc = self.take_doc();
+        let TokenType::Identifier(iden) = self.current() else {
+            self.error_at_current(CompileErrorKind::UnexpectedToken, "Expect method name.");
+            // skip the token so the class body loop makes progress
+            self.advance();
+            return;
+        };
+        let name = iden.to_string();
+        self.advance();
+        let class_name = self.classes.last().map(|class| class.name.clone()).unwrap_or_default();
+        self.document(DocTarget::Method, format!("{}.{}", class_name, name), doc);
+
+        let kind = if name == "init" {
+            FunctionKind::Initializer
+        } else {
+            FunctionKind::Method
+        };
+        self.function(kind, &name);
+        self.emit_name(Op::Method, &name);
+    }
+
+    fn function(&mut self, kind: FunctionKind, name: &str) {
+        self.compilers.push(Compiler::new(kind, name));
+        self.begin_scope();
+
+        self.consume(TokenType::LeftParen, "Expect '(' after function name.");
+        if !matches!(self.current(), TokenType::RightParen) {
+            loop {
+                self.compiler_mut().function.arity += 1;
+                if self.compiler().function.arity > MAX_ARGS {
+                    self.error_at_current(
+                        CompileErrorKind::TooManyArguments,
+                        "Can't have more than 255 parameters.",
+                    );
+                }
+                match self.current() {
+                    TokenType::Identifier(param) => {
+                        let param = param.to_string();
+                        self.advance();
+                        self.add_local(param);
+                    }
+                    _ => {
+                        self.error_at_current(
+                            CompileErrorKind::UnexpectedToken,
+                            "Expect parameter name.",
+                        );
+                    }
+                }
+                if !matches!(self.current(), TokenType::Comma) {
+                    break;
+                }
+                self.advance();
             }
         }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.");
+        self.consume(TokenType::LeftBrace, "Expect '{' before function body.");
+        self.block();
+        self.emit_return();
+
+        // no end_scope: the frame's slots are discarded on return
+        let compiler = self.compilers.pop().expect("no function being compiled");
+        let mut function = compiler.function;
+        function.upvalue_count = compiler.upvalues.len();
+        function.chunk.source = Rc::clone(&self.source);
+        let index = self.make_constant(Value::Function(Rc::new(function)));
+        self.emit(Op::Closure);
+        self.emit_u16(index);
+        for upvalue in compiler.upvalues {
+            self.emit_byte(upvalue.is_local as u8);
+            self.emit_byte(upvalue.index as u8);
+        }
+    }
+
+    fn add_local(&mut self, iden: String) {
+        let depth = self.scope_depth();
+        self.push_local(iden, depth);
+    }
+
+    fn push_local(&mut self, iden: String, depth: i32) {
+        let scope_depth = self.scope_depth();
+        let redeclared = self.compiler()
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth >= scope_depth)
+            .any(|local| local.name == iden);
+        if redeclared {
+            self.error(
+                CompileErrorKind::DuplicateVariable,
+                "Already a variable with this name in this scope.",
+            );
+        }
+        if self.compiler().locals.len() == MAX_LOCALS {
+            self.error(CompileErrorKind::TooManyLocals, "Too many local variables in function.");
+            return;
+        }
 
-        self.local_count += 1;
-        self.locals.push(Local {
+        self.compiler_mut().locals.push(Local {
             name: iden,
-            depth: self.scope_depth,
+            depth,
+            is_captured: false,
         });
     }
 
+    fn mark_initialized(&mut self) {
+        let scope_depth = self.scope_depth();
+        if let Some(local) = self.compiler_mut().locals.last_mut() {
+            if local.depth == UNINITIALIZED {
+                local.depth = scope_depth;
+            }
+        }
+    }
+
     fn statement(&mut self) {
+        if !self.enter_nesting() {
+            return;
+        }
         match self.current() {
             TokenType::Print => {
                 self.advance();
@@ -158,124 +781,257 @@ impl Parser {
                 self.advance();
                 self.while_statement();
             },
+            TokenType::For => {
+                self.advance();
+                self.for_statement();
+            },
+            TokenType::Return => {
+                self.advance();
+                self.return_statement();
+            },
+            TokenType::Break => {
+                self.advance();
+                self.break_statement();
+            },
+            TokenType::Continue => {
+                self.advance();
+                self.continue_statement();
+            },
             _ => {
                 self.expression_statement();
             },
         }
+        self.leave_nesting();
     }
 
     fn while_statement(&mut self) {
-        let loop_start = self.ops.len();
-        self.consume(TokenType::LeftParen);
+        let loop_start = self.code_len();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.");
         self.expression();
-        self.consume(TokenType::RightParen);
+        self.consume(TokenType::RightParen, "Expect ')' after condition.");
 
-        self.ops.push(Op::JumpIfFalse(0));
-        let begin_loop_body = self.ops.len() - 1;
-        self.ops.push(Op::Pop);
+        let exit_jump = self.emit_jump(Op::JumpIfFalse);
+        self.emit(Op::Pop);
 
+        self.begin_loop(loop_start);
         self.statement();
 
-        let jump_offset = self.ops.len() - begin_loop_body;
-        if let Op::JumpIfFalse(ref mut offset) = self.ops[begin_loop_body] {
-            *offset = jump_offset;
-        } else { unreachable!() }
+        self.emit_loop(loop_start);
+        self.patch_jump(exit_jump);
+        self.emit(Op::Pop);
+        self.end_loop();
+    }
 
-        self.ops.push(Op::Loop(self.ops.len() - (loop_start - 1) ));
-        self.ops.push(Op::Pop);
+    fn for_statement(&mut self) {
+        self.begin_scope();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.");
+        match self.current() {
+            TokenType::Semicolon => self.advance(),
+            TokenType::Var => {
+                self.advance();
+                self.var_declaration(None);
+            },
+            _ => self.expression_statement(),
+        }
+
+        let mut loop_start = self.code_len();
+        let mut exit_jump = None;
+        if let TokenType::Semicolon = self.current() {
+            self.advance();
+        } else {
+            self.expression();
+            self.consume(TokenType::Semicolon, "Expect ';' after loop condition.");
+            exit_jump = Some(self.emit_jump(Op::JumpIfFalse));
+            self.emit(Op::Pop);
+        }
 
+        if let TokenType::RightParen = self.current() {
+            self.advance();
+        } else {
+            // the increment runs after the body, so jump over it for now
+            let body_jump = self.emit_jump(Op::Jump);
+            let increment_start = self.code_len();
+            self.expression();
+            self.emit(Op::Pop);
+            self.consume(TokenType::RightParen, "Expect ')' after for clauses.");
+
+            self.emit_loop(loop_start);
+            loop_start = increment_start;
+            self.patch_jump(body_jump);
+        }
+
+        self.begin_loop(loop_start);
+        self.statement();
+        self.emit_loop(loop_start);
+
+        if let Some(exit_jump) = exit_jump {
+            self.patch_jump(exit_jump);
+            self.emit(Op::Pop);
+        }
+        self.end_loop();
+        self.end_scope();
+    }
+
+    fn begin_loop(&mut self, start: usize) {
+        let scope_depth = self.scope_depth();
+        self.compiler_mut().loops.push(LoopContext {
+            start,
+            scope_depth,
+            breaks: Vec::new(),
+        });
+    }
+
+    fn end_loop(&mut self) {
+        let lp = self.compiler_mut().loops.pop().expect("loop context");
+        for jump in lp.breaks {
+            self.patch_jump(jump);
+        }
+    }
+
+    /// Emits the pops for the locals declared inside the innermost loop body
+    /// without forgetting them, since compilation of the body continues.
+    fn discard_loop_locals(&mut self, depth: i32) {
+        let ops: Vec<Op> = self.compiler().locals.iter()
+            .rev()
+            .take_while(|local| local.depth > depth)
+            .map(|local| if local.is_captured { Op::CloseUpvalue } else { Op::Pop })
+            .collect();
+        for op in ops {
+            self.emit(op);
+        }
+    }
+
+    fn break_statement(&mut self) {
+        let depth = match self.compiler().loops.last() {
+            Some(lp) => lp.scope_depth,
+            None => {
+                self.error(CompileErrorKind::InvalidBreak, "Can't use 'break' outside of a loop.");
+                return;
+            }
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after 'break'.");
+        self.discard_loop_locals(depth);
+        let jump = self.emit_jump(Op::Jump);
+        self.compiler_mut().loops.last_mut().unwrap().breaks.push(jump);
+    }
+
+    fn continue_statement(&mut self) {
+        let (start, depth) = match self.compiler().loops.last() {
+            Some(lp) => (lp.start, lp.scope_depth),
+            None => {
+                self.error(CompileErrorKind::InvalidContinue, "Can't use 'continue' outside of a loop.");
+                return;
+            }
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after 'continue'.");
+        self.discard_loop_locals(depth);
+        self.emit_loop(start);
+    }
+
+    fn return_statement(&mut self) {
+        if self.compiler().kind == FunctionKind::Script {
+            self.error(CompileErrorKind::InvalidReturn, "Can't return from top-level code.");
+        }
+
+        if let TokenType::Semicolon = self.current() {
+            self.advance();
+            self.emit_return();
+            return;
+        }
+        if self.compiler().kind == FunctionKind::Initializer {
+            self.error(
+                CompileErrorKind::InvalidReturn,
+                "Can't return a value from an initializer.",
+            );
+        }
+        self.expression();
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.");
+        self.emit(Op::Return);
     }
 
     fn if_statement(&mut self) {
-        self.consume(TokenType::LeftParen);
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.");
         self.expression();
-        self.consume(TokenType::RightParen);
+        self.consume(TokenType::RightParen, "Expect ')' after condition.");
 
-        self.ops.push(Op::JumpIfFalse(0));
-        let then_jump = self.ops.len() - 1;
-        self.ops.push(Op::Pop);
+        let then_jump = self.emit_jump(Op::JumpIfFalse);
+        self.emit(Op::Pop);
 
         self.statement();
 
-        self.ops.push(Op::Jump(0));
-        let else_jump = self.ops.len() - 1;
-        self.ops.push(Op::Pop);
-
-        if let Op::JumpIfFalse(ref mut offset) = self.ops[then_jump] {
-            *offset = else_jump - then_jump;
-        } else { unreachable!() }
-
+        let else_jump = self.emit_jump(Op::Jump);
+        self.patch_jump(then_jump);
+        self.emit(Op::Pop);
 
         if let TokenType::Else = self.current() {
             self.advance();
             self.statement();
         }
 
-        
-        let jump_offset = self.ops.len() - 1 - else_jump;
-        if let Op::Jump(ref mut offset) = self.ops[else_jump] {
-            *offset = jump_offset;
-        } else { unreachable!() }
+        self.patch_jump(else_jump);
     }
 
     fn and(&mut self) {
-        self.ops.push(Op::JumpIfFalse(0));
-        let jump = self.ops.len() - 1;
-        self.ops.push(Op::Pop);
+        let jump = self.emit_jump(Op::JumpIfFalse);
+        self.emit(Op::Pop);
 
         self.parse_precedence(Precedence::And);
 
-        let off = self.ops.len() - 1 - jump;
-        if let Op::JumpIfFalse(ref mut offset) = self.ops[jump] {
-            *offset = off;
-        } else { unreachable!() }
+        self.patch_jump(jump);
     }
 
     fn or(&mut self) {
-        self.ops.push(Op::JumpIfTrue(0));
-        let jump = self.ops.len() - 1;
-        self.ops.push(Op::Pop);
+        let jump = self.emit_jump(Op::JumpIfTrue);
+        self.emit(Op::Pop);
 
         self.parse_precedence(Precedence::And);
 
-        let off = self.ops.len() - 1 - jump;
-        if let Op::JumpIfTrue(ref mut offset) = self.ops[jump] {
-            *offset = off;
-        } else { unreachable!() }
+        self.patch_jump(jump);
     }
 
     fn block(&mut self) {
+        if !self.enter_nesting() {
+            return;
+        }
         loop {
             match self.current() {
                 TokenType::RightBrace | TokenType::Eof =>  break,
                 _ => self.declaration(),
             }
         }
-        self.consume(TokenType::RightBrace);
+        self.consume(TokenType::RightBrace, "Expect '}' after block.");
+        self.leave_nesting();
     }
     fn begin_scope(&mut self) {
-        self.scope_depth += 1;
+        self.compiler_mut().scope_depth += 1;
 	}
     fn end_scope(&mut self) {
-        self.scope_depth -= 1;
+        self.compiler_mut().scope_depth -= 1;
 
-        while self.local_count > 0 && self.locals.last().unwrap().depth > self.scope_depth {
-            self.local_count -= 1;
-            self.locals.pop();
-            self.ops.push(Op::Pop);
+        let scope_depth = self.scope_depth();
+        while let Some(local) = self.compiler().locals.last() {
+            if local.depth <= scope_depth {
+                break;
+            }
+            let op = if local.is_captured { Op::CloseUpvalue } else { Op::Pop };
+            self.compiler_mut().locals.pop();
+            self.emit(op);
         }
 	}
 
     fn expression_statement(&mut self) {
         self.expression();
-        self.consume(TokenType::Semicolon);
-        self.ops.push(Op::Pop);
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.");
+        if self.compilers.len() == 1 && self.scope_depth() == 0 {
+            self.last_expression_pop = Some(self.code_len());
+        }
+        self.emit(Op::Pop);
     }
 
     fn print_statement(&mut self) {
         self.expression();
-        self.consume(TokenType::Semicolon);
-        self.ops.push(Op::Print);
+        self.consume(TokenType::Semicolon, "Expect ';' after value.");
+        self.emit(Op::Print);
     }
 
     fn expression(&mut self) {
@@ -284,109 +1040,289 @@ impl Parser {
 
     fn unary(&mut self) {
         let operator_type = self.prev().clone();
-        self.expression();
+        let operator = self.prev.span;
+        self.parse_precedence(Precedence::Unary);
         match operator_type {
-            TokenType::Minus => self.ops.push(Op::Negate),
-            TokenType::Bang => self.ops.push(Op::Not),
-            _ => unimplemented!(),
+            TokenType::Minus => self.emit_at(Op::Negate, operator),
+            TokenType::Bang => self.emit_at(Op::Not, operator),
+            _ => unreachable!(),
+        }
+    }
+
+    fn call(&mut self) {
+        let paren = self.prev.span;
+        let arg_count = self.argument_list();
+        self.emit_at(Op::Call, paren);
+        self.emit_byte(arg_count as u8);
+    }
+
+    fn argument_list(&mut self) -> usize {
+        let mut arg_count = 0;
+        if !matches!(self.current(), TokenType::RightParen) {
+            loop {
+                self.expression();
+                if arg_count == MAX_ARGS {
+                    self.error(
+                        CompileErrorKind::TooManyArguments,
+                        "Can't have more than 255 arguments.",
+                    );
+                }
+                arg_count += 1;
+                if !matches!(self.current(), TokenType::Comma) {
+                    break;
+                }
+                self.advance();
+            }
         }
+        self.consume(TokenType::RightParen, "Expect ')' after arguments.");
+        arg_count
     }
 
     fn grouping(&mut self) {
         self.expression();
-        self.consume(TokenType::RightParen);
+        self.consume(TokenType::RightParen, "Expect ')' after expression.");
     }
 
     fn binary(&mut self) {
         let operator_type = self.prev().clone();
+        let operator = self.prev.span;
         let rule = parse_rules(&operator_type);
         self.parse_precedence(rule.precedence.next());
         match operator_type {
-            TokenType::Plus => self.ops.push(Op::Add),
-            TokenType::Minus => self.ops.push(Op::Subtract),
-            TokenType::Star => self.ops.push(Op::Multiply),
-            TokenType::Slash => self.ops.push(Op::Divide),
-            TokenType::EqualEqual => self.ops.push(Op::Equal),
+            TokenType::Plus => self.emit_at(Op::Add, operator),
+            TokenType::Minus => self.emit_at(Op::Subtract, operator),
+            TokenType::Star => self.emit_at(Op::Multiply, operator),
+            TokenType::Slash => self.emit_at(Op::Divide, operator),
+            TokenType::EqualEqual => self.emit_at(Op::Equal, operator),
             TokenType::BangEqual => {
-                self.ops.push(Op::Equal);
-                self.ops.push(Op::Not);
+                self.emit_at(Op::Equal, operator);
+                self.emit_at(Op::Not, operator);
             }
-            TokenType::Greater => self.ops.push(Op::Greater),
+            TokenType::Greater => self.emit_at(Op::Greater, operator),
             TokenType::GreaterEqual => {
-                self.ops.push(Op::Less);
-                self.ops.push(Op::Not);
+                self.emit_at(Op::Less, operator);
+                self.emit_at(Op::Not, operator);
             }
-            TokenType::Less => self.ops.push(Op::Less),
+            TokenType::Less => self.emit_at(Op::Less, operator),
             TokenType::LessEqual => {
-                self.ops.push(Op::Greater);
-                self.ops.push(Op::Not);
+                self.emit_at(Op::Greater, operator);
+                self.emit_at(Op::Not, operator);
             }
-            _ => panic!("Expected operator, found: {:?}", self.prev()),
+            _ => unreachable!(),
         }
     }
 
     fn literal(&mut self) {
         match self.prev() {
-            TokenType::True => self.ops.push(Op::Constant(Value::Bool(true))),
-            TokenType::False => self.ops.push(Op::Constant(Value::Bool(false))),
-            TokenType::Nil => self.ops.push(Op::Constant(Value::Nil)),
-            _ => panic!("Expected literal, found: {:?}", self.prev()),
+            TokenType::True => self.emit(Op::True),
+            TokenType::False => self.emit(Op::False),
+            TokenType::Nil => self.emit(Op::Nil),
+            _ => unreachable!(),
         }
     }
 
     fn number(&mut self) {
         if let TokenType::Number(n) = self.prev() {
-            self.ops.push(Op::Constant(Value::Number(*n)));
+            self.emit_constant(Value::Number(*n));
             return;
         }
-        panic!("Expected number");
+        unreachable!();
     }
 
     fn string(&mut self) {
         if let TokenType::String(s) = self.prev() {
-            self.ops.push(Op::Constant(Value::String(s.clone())));
+            let s = s.to_string();
+            self.emit_string(&s);
             return;
         }
-        panic!("Expected string");
+        unreachable!();
     }
 
-    fn variable(&mut self, can_assign: bool) {
-        self.named_variable(can_assign);
+    /// Lowers `"a ${x} b"` to `"a " + str(x) + " b"`.
+    fn interpolation(&mut self) {
+        let mut first = true;
+        while let TokenType::Interpolation(s) = self.prev() {
+            let s = s.to_string();
+            if first || !s.is_empty() {
+                self.emit_string(&s);
+                if !first {
+                    self.emit(Op::Add);
+                }
+            }
+            first = false;
+
+            self.expression();
+            self.emit(Op::Stringify);
+            self.emit(Op::Add);
+
+            match self.current() {
+                TokenType::Interpolation(_) => self.advance(),
+                TokenType::String(s) => {
+                    let s = s.to_string();
+                    self.advance();
+                    if !s.is_empty() {
+                        self.emit_string(&s);
+                        self.emit(Op::Add);
+                    }
+                    return;
+                }
+                _ => {
+                    self.error_at_current(CompileErrorKind::UnexpectedToken, "Expect '}' after interpolated expression.");
+                    return;
+                }
+            }
+        }
     }
 
-    fn named_variable(&mut self, can_assign: bool) {
+    fn variable(&mut self, can_assign: bool) {
         let iden = match self.prev() {
-            TokenType::Identifier(iden) =>  iden.clone(),
-            _ => panic!("Expected identifier"),
+            TokenType::Identifier(iden) =>  iden.to_string(),
+            _ => unreachable!(),
         };
+        self.named_variable(iden, can_assign);
+    }
 
-        let (set_op, get_op) = match self.resolve_local(&iden) {
-            Some(local) => {
-                (Op::SetLocal(local), Op::GetLocal(local))
-            },
+    fn this(&mut self) {
+        if self.classes.is_empty() {
+            self.error(CompileErrorKind::InvalidThis, "Can't use 'this' outside of a class.");
+            return;
+        }
+        self.named_variable("this".to_string(), false);
+    }
+
+    fn super_(&mut self) {
+        match self.classes.last() {
             None => {
-                (Op::SetGlobal(iden.clone()), Op::GetGlobal(iden))
-            },
+                self.error(CompileErrorKind::InvalidSuper, "Can't use 'super' outside of a class.");
+            }
+            Some(class) if !class.has_superclass => {
+                self.error(
+                    CompileErrorKind::InvalidSuper,
+                    "Can't use 'super' in a class with no superclass.",
+                );
+            }
+            Some(_) => {}
+        }
+
+        self.consume(TokenType::Dot, "Expect '.' after 'super'.");
+        let TokenType::Identifier(iden) = self.current() else {
+            self.error_at_current(
+                CompileErrorKind::UnexpectedToken,
+                "Expect superclass method name.",
+            );
+            return;
+        };
+        let name = iden.to_string();
+        self.advance();
+
+        self.named_variable("this".to_string(), false);
+        self.named_variable("super".to_string(), false);
+        self.emit_name(Op::GetSuper, &name);
+    }
+
+    fn dot(&mut self, can_assign: bool) {
+        let TokenType::Identifier(iden) = self.current() else {
+            self.error_at_current(
+                CompileErrorKind::UnexpectedToken,
+                "Expect property name after '.'.",
+            );
+            return;
         };
+        let name = iden.to_string();
+        self.advance();
+        let property = self.prev.span;
 
         match self.current() {
             TokenType::Equal if can_assign => {
                 self.advance();
                 self.expression();
-                self.ops.push(set_op);
+                let index = self.name_constant(&name);
+                self.emit_at(Op::SetProperty, property);
+                self.emit_u16(index);
             },
             _ => {
-                self.ops.push(get_op);
+                self.emit_name(Op::GetProperty, &name);
             },
         }
     }
 
-    fn resolve_local(&mut self, name: &str) -> Option<usize>{
-        self.locals.iter()
+    fn named_variable(&mut self, iden: String, can_assign: bool) {
+        let span = self.prev.span;
+        let innermost = self.compilers.len() - 1;
+        let (set_op, get_op, arg) = if let Some(local) = self.resolve_local(innermost, &iden) {
+            (Op::SetLocal, Op::GetLocal, local)
+        } else if let Some(upvalue) = self.resolve_upvalue(innermost, &iden) {
+            (Op::SetUpvalue, Op::GetUpvalue, upvalue)
+        } else {
+            (Op::SetGlobal, Op::GetGlobal, self.global_slot(&iden) as usize)
+        };
+
+        let op = match self.current() {
+            TokenType::Equal if can_assign => {
+                self.advance();
+                self.expression();
+                set_op
+            },
+            _ => get_op,
+        };
+        self.emit_at(op, span);
+        // globals have u16 slots, locals and upvalues u8 ones
+        if let Op::SetGlobal | Op::GetGlobal = op {
+            self.emit_u16(arg as u16);
+        } else {
+            self.emit_byte(arg as u8);
+        }
+    }
+
+    fn resolve_local(&mut self, compiler: usize, name: &str) -> Option<usize>{
+        let (index, local) = self.compilers[compiler].locals.iter()
             .enumerate()
             .rev()
-            .find(|(_, local)| local.name == name)
-            .map(|(i, _)| i)
+            .find(|(_, local)| local.name == name)?;
+        if local.depth == UNINITIALIZED {
+            self.error(
+                CompileErrorKind::UninitializedLocal,
+                "Can't read local variable in its own initializer.",
+            );
+        }
+        Some(index)
+    }
+
+    /// Looks `name` up in the enclosing functions, threading an upvalue
+    /// through every function between the one that declares it and
+    /// `compiler`.
+    fn resolve_upvalue(&mut self, compiler: usize, name: &str) -> Option<usize> {
+        if compiler == 0 {
+            return None;
+        }
+
+        if let Some(local) = self.resolve_local(compiler - 1, name) {
+            self.compilers[compiler - 1].locals[local].is_captured = true;
+            return Some(self.add_upvalue(compiler, local, true));
+        }
+        if let Some(upvalue) = self.resolve_upvalue(compiler - 1, name) {
+            return Some(self.add_upvalue(compiler, upvalue, false));
+        }
+        None
+    }
+
+    fn add_upvalue(&mut self, compiler: usize, index: usize, is_local: bool) -> usize {
+        let upvalue = UpvalueRef { index, is_local };
+        let upvalues = &self.compilers[compiler].upvalues;
+        if let Some(existing) = upvalues.iter().position(|u| *u == upvalue) {
+            return existing;
+        }
+        if upvalues.len() == MAX_UPVALUES {
+            self.error(
+                CompileErrorKind::TooManyUpvalues,
+                "Too many closure variables in function.",
+            );
+            return 0;
+        }
+
+        let upvalues = &mut self.compilers[compiler].upvalues;
+        upvalues.push(upvalue);
+        upvalues.len() - 1
     }
 
 }
@@ -433,22 +1369,32 @@ enum RuleFunc {
     Binary,
     Grouping,
     String,
+    Interpolation,
     And,
     Or,
+    Call,
+    Dot,
+    This,
+    Super,
 }
 
 impl RuleFunc {
-    fn parse(&self, p: &mut Parser, can_assign: bool) {
+    fn parse(&self, p: &mut Parser<'_, '_>, can_assign: bool) {
         match self {
             Self::Number => Parser::number(p),
             Self::Variable => Parser::variable(p, can_assign),
             Self::Literal => Parser::literal(p),
             Self::Unary => Parser::unary(p),
+            Self::Interpolation => Parser::interpolation(p),
             Self::Binary => Parser::binary(p),
             Self::Grouping => Parser::grouping(p),
             Self::String => Parser::string(p),
             Self::And => Parser::and(p),
             Self::Or => Parser::or(p),
+            Self::Call => Parser::call(p),
+            Self::Dot => Parser::dot(p, can_assign),
+            Self::This => Parser::this(p),
+            Self::Super => Parser::super_(p),
         }
     }
 }
@@ -464,13 +1410,8 @@ fn parse_rules(t: &TokenType) -> ParseRule {
     match t {
         TokenType::LeftParen => ParseRule {
             prefix: Some(RuleFunc::Grouping),
-            infix: None,
-            precedence: Precedence::None,
-        },
-        TokenType::RightParen => ParseRule {
-            prefix: None,
-            infix: None,
-            precedence: Precedence::None,
+            infix: Some(RuleFunc::Call),
+            precedence: Precedence::Call,
         },
         TokenType::Minus => ParseRule {
             prefix: Some(RuleFunc::Unary),
@@ -526,11 +1467,31 @@ fn parse_rules(t: &TokenType) -> ParseRule {
             infix: None,
             precedence: Precedence::None,
         },
+        TokenType::Dot => ParseRule {
+            prefix: None,
+            infix: Some(RuleFunc::Dot),
+            precedence: Precedence::Call,
+        },
+        TokenType::This => ParseRule {
+            prefix: Some(RuleFunc::This),
+            infix: None,
+            precedence: Precedence::None,
+        },
+        TokenType::Super => ParseRule {
+            prefix: Some(RuleFunc::Super),
+            infix: None,
+            precedence: Precedence::None,
+        },
         TokenType::String(_) => ParseRule {
             prefix: Some(RuleFunc::String),
             infix: None,
             precedence: Precedence::None,
         },
+        TokenType::Interpolation(_) => ParseRule {
+            prefix: Some(RuleFunc::Interpolation),
+            infix: None,
+            precedence: Precedence::None,
+        },
         TokenType::And => ParseRule {
             prefix: None,
             infix: Some(RuleFunc::And),
@@ -541,14 +1502,23 @@ fn parse_rules(t: &TokenType) -> ParseRule {
             infix: Some(RuleFunc::Or),
             precedence: Precedence::Or,
         },
-        TokenType::Eof | TokenType::Semicolon | TokenType::Equal => ParseRule {
+        _ => ParseRule {
             prefix: None,
             infix: None,
             precedence: Precedence::None,
         },
-        t => {
-            println!("Unknown token: {:?}", t);
-            unimplemented!();
-        }
     }
 }
+
+/// Scans and compiles `source` on its own, e.g. to inspect or disassemble
+/// it. Its global slots are private to this call, so use `compile_with` for
+/// chunks that will be run by a `VM`.
+pub fn compile(source: &str) -> Result<Chunk, Vec<CompileError>> {
+    compile_with(source, &mut Globals::new(), &mut Interner::new())
+}
+
+/// Scans and compiles `source` into a chunk for the VM owning `globals` and
+/// `strings`.
+pub fn compile_with(source: &str, globals: &mut Globals, strings: &mut Interner) -> Result<Chunk, Vec<CompileError>> {
+    Parser::new(Scanner::new(source), globals, strings).compile()
+}
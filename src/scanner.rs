@@ -1,177 +1,673 @@
-use crate::token::TokenType;
+use std::borrow::Cow;
 
-pub struct Scanner {
-    chars: Vec<char>,
+use crate::error::CompileErrorKind;
+use crate::token::{Span, Token, TokenType};
+
+/// Scans tokens lazily from the source. Lexemes are borrowed from the
+/// source wherever possible; the last token is always `Eof`.
+pub struct Scanner<'src> {
+    source: &'src str,
+    start: Position,
+    pos: Position,
+    // brace depth inside each `${ ... }` being scanned, innermost last
+    interpolations: Vec<usize>,
+    done: bool,
 }
 
-struct ScannerIter<'a> {
-    scanner: &'a Scanner,
-    index: usize,
-    stack: Vec<usize>,
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    byte: usize,
+    line: usize,
+    column: usize,
 }
 
-impl ScannerIter<'_> {
-    fn peek(&self) -> Option<&char> {
-        self.scanner.chars.get(self.index)
+impl<'src> Scanner<'src> {
+    pub fn new(source: &'src str) -> Self {
+        let start = Position {
+            byte: 0,
+            line: 1,
+            column: 1,
+        };
+        Scanner {
+            source,
+            start,
+            pos: start,
+            interpolations: Vec::new(),
+            done: false,
+        }
     }
-    fn prev(&mut self) {
-        self.index -= 1;
+
+    /// The text being scanned.
+    pub fn source(&self) -> &'src str {
+        self.source
     }
-    fn save(&mut self) {
-        self.stack.push(self.index);
+
+    /// Scans the whole source up front.
+    pub fn scan_tokens(self) -> Vec<Token<'src>> {
+        self.collect()
     }
-    fn restore(&mut self) {
-        self.index = self.stack.pop().unwrap();
+
+    fn peek(&self) -> Option<char> {
+        match self.source.as_bytes().get(self.pos.byte) {
+            Some(b) if b.is_ascii() => Some(*b as char),
+            Some(_) => self.source[self.pos.byte..].chars().next(),
+            None => None,
+        }
     }
-}
 
-impl<'a> Iterator for ScannerIter<'a> {
-    type Item = &'a char;
-    fn next(&mut self) -> Option<Self::Item> {
-        self.scanner.chars.get(self.index).map(|c| {
-            self.index += 1;
-            c
-        })
+    fn peek_next(&self) -> Option<char> {
+        let mut chars = self.source[self.pos.byte..].chars();
+        chars.next();
+        chars.next()
     }
-}
 
-impl Scanner {
-    pub fn new(str: &str) -> Scanner {
-        Scanner {
-            chars: str.chars().collect(),
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos.byte += c.len_utf8();
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
         }
+        Some(c)
     }
 
-    fn iter(&self) -> ScannerIter {
-        ScannerIter {
-            scanner: self,
-            index: 0,
-            stack: Vec::new(),
+    fn matches(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.advance();
+            return true;
         }
+        false
     }
 
-    pub fn scan_tokens(&self) -> Vec<TokenType> {
-        let mut tokens = Vec::new();
-        let mut iter_chars = self.iter();
+    fn skip_while(&mut self, pred: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&pred) {
+            self.advance();
+        }
+    }
 
-        macro_rules! match_next {
-            ($cond:expr => $then:expr; $else:expr) => {
-                match iter_chars.peek() {
-                    Some($cond) => {
-                        iter_chars.next();
-                        tokens.push($then);
-                    }
-                    _ => {
-                        tokens.push($else);
-                    }
-                }
-            };
+    /// The source text of the token being scanned.
+    fn lexeme(&self) -> &'src str {
+        let source = self.source;
+        &source[self.start.byte..self.pos.byte]
+    }
+
+    fn token(&self, kind: TokenType<'src>) -> Token<'src> {
+        Token {
+            kind,
+            span: Span {
+                line: self.start.line,
+                column: self.start.column,
+                start: self.start.byte,
+                end: self.pos.byte,
+            },
         }
+    }
 
-        macro_rules! match_keyword {
-            ($chr:expr, $($keyw:expr => $token:expr),* ) => {
-                $(
-                    if $chr == $keyw.chars().next().unwrap() {
-                        iter_chars.save();
-                        let maybe_kw = std::iter::once(&$chr)
-                            .chain(iter_chars.by_ref().take($keyw.len()-1))
-                            .collect::<String>();
-                        if maybe_kw == $keyw {
-                            tokens.push($token);
-                            continue;
-                        } else {
-                            iter_chars.restore();
-                        }
-                    }
-                )*
+    fn error(&self, kind: CompileErrorKind, msg: String) -> Token<'src> {
+        self.token(TokenType::Error(kind, msg))
+    }
+
+    /// Scans the next token, or returns `None` for whitespace and comments.
+    fn scan_token(&mut self) -> Option<Token<'src>> {
+        let Some(c) = self.advance() else {
+            self.done = true;
+            return Some(self.token(TokenType::Eof));
+        };
+        let kind = match c {
+            '(' => TokenType::LeftParen,
+            ')' => TokenType::RightParen,
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                TokenType::LeftBrace
             }
-        }
+            // closes an interpolation, the rest of the string follows
+            '}' if self.interpolations.last() == Some(&0) => {
+                self.interpolations.pop();
+                return Some(self.string());
+            }
+            '}' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth -= 1;
+                }
+                TokenType::RightBrace
+            }
+            ',' => TokenType::Comma,
+            '.' => TokenType::Dot,
+            '-' => TokenType::Minus,
+            '+' => TokenType::Plus,
+            ';' => TokenType::Semicolon,
+            '/' => match self.peek() {
+                Some('/') => return self.line_comment(),
+                Some('*') => {
+                    self.advance();
+                    return self.block_comment();
+                }
+                _ => TokenType::Slash,
+            },
+            '*' => TokenType::Star,
+            '!' if self.matches('=') => TokenType::BangEqual,
+            '!' => TokenType::Bang,
+            '=' if self.matches('=') => TokenType::EqualEqual,
+            '=' => TokenType::Equal,
+            '<' if self.matches('=') => TokenType::LessEqual,
+            '<' => TokenType::Less,
+            '>' if self.matches('=') => TokenType::GreaterEqual,
+            '>' => TokenType::Greater,
+            '"' => return Some(self.string()),
+            '0'..='9' => return Some(self.number(c)),
+            'a'..='z' | 'A'..='Z' | '_' => {
+                self.skip_while(|chr| chr.is_alphanumeric() || chr == '_');
+                let iden = self.lexeme();
+                keyword(iden).unwrap_or(TokenType::Identifier(iden))
+            }
+
+            ' ' | '\r' | '\t' | '\n' => return None,
+
+            _ => {
+                return Some(self.error(
+                    CompileErrorKind::UnexpectedCharacter,
+                    "Unexpected character.".to_string(),
+                ))
+            }
+        };
+        Some(self.token(kind))
+    }
+
+    fn line_comment(&mut self) -> Option<Token<'src>> {
+        self.skip_while(|chr| chr != '\n');
+        // `///` is a doc comment, but `////` is a plain one
+        let text = self.lexeme().strip_prefix("///").filter(|text| !text.starts_with('/'))?;
+        Some(self.token(TokenType::DocComment(text.strip_prefix(' ').unwrap_or(text))))
+    }
 
-        macro_rules! take_while {
-            ($chr:expr, $checkfun:expr) => {{
-                let s: String = std::iter::once(&$chr)
-                    .chain(iter_chars.by_ref().take_while($checkfun))
-                    .collect();
-                if iter_chars.peek().is_some() {
-                    iter_chars.prev();
+    /// Skips the rest of a `/* */` comment, including nested ones, and
+    /// reports an error if the input ends first.
+    fn block_comment(&mut self) -> Option<Token<'src>> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.advance() {
+                Some('/') if self.matches('*') => depth += 1,
+                Some('*') if self.matches('/') => depth -= 1,
+                Some(_) => {}
+                None => {
+                    return Some(self.error(
+                        CompileErrorKind::UnterminatedComment,
+                        "Unterminated block comment.".to_string(),
+                    ))
                 }
-                s
-            }};
+            }
         }
+        None
+    }
 
-        while let Some(c) = iter_chars.next() {
+    /// Scans a string literal up to the closing quote or the start of an
+    /// interpolation, processing escape sequences. The contents are only
+    /// copied if they contain escapes, and only the first invalid escape is
+    /// reported.
+    fn string(&mut self) -> Token<'src> {
+        let source = self.source;
+        let content_start = self.pos.byte;
+        let mut escaped: Option<String> = None;
+        let mut error = None;
+        let (content_end, interpolation) = loop {
+            let before = self.pos.byte;
+            let Some(c) = self.advance() else {
+                return self.error(CompileErrorKind::UnterminatedString, "Unterminated string.".to_string());
+            };
             match c {
-                '(' => tokens.push(TokenType::LeftParen),
-                ')' => tokens.push(TokenType::RightParen),
-                '{' => tokens.push(TokenType::LeftBrace),
-                '}' => tokens.push(TokenType::RightBrace),
-                ',' => tokens.push(TokenType::Comma),
-                '.' => tokens.push(TokenType::Dot),
-                '-' => tokens.push(TokenType::Minus),
-                '+' => tokens.push(TokenType::Plus),
-                ';' => tokens.push(TokenType::Semicolon),
-                '/' => {
-                    if let Some('/') = iter_chars.peek() {
-                        let _: String = iter_chars.by_ref().take_while(|c| **c != '\n').collect();
-                    } else {
-                        tokens.push(TokenType::Slash);
+                '"' => break (before, false),
+                '$' if self.matches('{') => break (before, true),
+                '\\' => {
+                    let c = self.escape();
+                    let s = escaped.get_or_insert_with(|| source[content_start..before].to_string());
+                    match c {
+                        Ok(c) => s.push(c),
+                        Err(msg) => {
+                            error.get_or_insert(msg);
+                        }
                     }
                 }
-                '*' => tokens.push(TokenType::Star),
-                '!' => match_next!('=' => TokenType::BangEqual; TokenType::Bang),
-                '=' => match_next!('=' => TokenType::EqualEqual; TokenType::Equal),
-                '<' => match_next!('=' => TokenType::LessEqual; TokenType::Less),
-                '>' => match_next!('=' => TokenType::GreaterEqual; TokenType::Greater),
-                '"' => {
-                    tokens.push(TokenType::String(
-                        take_while!(*c, |chr| **chr != '"')
-                            .get(1..)
-                            .unwrap()
-                            .to_string(),
-                    ));
-                    iter_chars.next();
-                }
-                '0'..='9' => {
-                    tokens.push(TokenType::Number(
-                        take_while!(*c, |chr| chr.is_alphanumeric() || **chr == '_')
-                            .parse::<f64>()
-                            .unwrap(),
-                    ));
-                }
-                'a'..='z' | 'A'..='Z' | '_' => {
-                    match_keyword!(*c,
-                        "and" => TokenType::And,
-                        "class" => TokenType::Class,
-                        "else" => TokenType::Else,
-                        "false" => TokenType::False,
-                        "for" => TokenType::For,
-                        "fun" => TokenType::Fun,
-                        "if" => TokenType::If,
-                        "nil" => TokenType::Nil,
-                        "or" => TokenType::Or,
-                        "print" => TokenType::Print,
-                        "return" => TokenType::Return,
-                        "super" => TokenType::Super,
-                        "this" => TokenType::This,
-                        "true" => TokenType::True,
-                        "var" => TokenType::Var,
-                        "while" => TokenType::While
-                    );
-
-                    tokens.push(TokenType::Identifier(take_while!(*c, |chr| chr
-                        .is_alphanumeric()
-                        || **chr == '_')));
+                c => {
+                    if let Some(s) = escaped.as_mut() {
+                        s.push(c);
+                    }
                 }
+            }
+        };
+
+        if interpolation {
+            self.interpolations.push(0);
+        }
+        if let Some(msg) = error {
+            return self.error(CompileErrorKind::InvalidEscape, msg);
+        }
+        let s = match escaped {
+            Some(s) => Cow::Owned(s),
+            None => Cow::Borrowed(&source[content_start..content_end]),
+        };
+        if interpolation {
+            self.token(TokenType::Interpolation(s))
+        } else {
+            self.token(TokenType::String(s))
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, String> {
+        // at EOF the caller reports the unterminated string instead
+        let Some(c) = self.advance() else {
+            return Ok('\\');
+        };
+        Ok(match c {
+            '"' | '\\' | '$' => c,
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            'u' => return self.unicode_escape(),
+            c => return Err(format!("Invalid escape sequence '\\{}'.", c)),
+        })
+    }
+
+    /// Parses the `{XXXX}` part of a `\u{XXXX}` escape.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        if !self.matches('{') {
+            return Err("Expect '{' after '\\u'.".to_string());
+        }
+        let hex_start = self.pos.byte;
+        self.skip_while(|c| c.is_ascii_hexdigit());
+        let hex = &self.source[hex_start..self.pos.byte];
+        if !self.matches('}') {
+            return Err("Expect '}' after unicode escape.".to_string());
+        }
+        if hex.is_empty() || hex.len() > 6 {
+            return Err("Unicode escape must have 1 to 6 hex digits.".to_string());
+        }
+        u32::from_str_radix(hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("Invalid unicode code point '{}'.", hex))
+    }
+
+    fn number(&mut self, first: char) -> Token<'src> {
+        // take everything that could belong to the literal and validate it
+        // afterwards, so `1_x` or `0b12` are reported as a whole instead of
+        // splitting into several tokens
+        let radix = first == '0' && matches!(self.peek(), Some('x' | 'X' | 'b' | 'B'));
+        let mut fraction = !radix;
+        let mut prev = first;
+        while let Some(chr) = self.peek() {
+            let takes = match chr {
+                '.' => fraction && self.peek_next().is_some_and(|c| c.is_ascii_digit()),
+                '+' | '-' => !radix && matches!(prev, 'e' | 'E'),
+                _ => chr.is_alphanumeric() || chr == '_',
+            };
+            if !takes {
+                break;
+            }
+            if !radix && matches!(chr, '.' | 'e' | 'E') {
+                fraction = false;
+            }
+            prev = chr;
+            self.advance();
+        }
+
+        let n = self.lexeme();
+        match parse_number(n) {
+            Ok(n) => self.token(TokenType::Number(n)),
+            Err(reason) => self.error(
+                CompileErrorKind::InvalidNumber,
+                format!("Invalid number literal '{}': {}.", n, reason),
+            ),
+        }
+    }
+}
+
+impl<'src> Iterator for Scanner<'src> {
+    type Item = Token<'src>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.start = self.pos;
+            if let Some(token) = self.scan_token() {
+                return Some(token);
+            }
+        }
+        None
+    }
+}
 
-                ' ' | '\r' | '\t' | '\n' => {}
+/// Classifies a complete identifier, so keywords only match when the
+/// whole word is spelled out (`android` is an identifier, not `and`).
+fn keyword<'src>(iden: &str) -> Option<TokenType<'src>> {
+    Some(match iden {
+        "and" => TokenType::And,
+        "break" => TokenType::Break,
+        "class" => TokenType::Class,
+        "continue" => TokenType::Continue,
+        "else" => TokenType::Else,
+        "false" => TokenType::False,
+        "for" => TokenType::For,
+        "fun" => TokenType::Fun,
+        "if" => TokenType::If,
+        "nil" => TokenType::Nil,
+        "or" => TokenType::Or,
+        "print" => TokenType::Print,
+        "return" => TokenType::Return,
+        "super" => TokenType::Super,
+        "this" => TokenType::This,
+        "true" => TokenType::True,
+        "var" => TokenType::Var,
+        "while" => TokenType::While,
+        _ => return None,
+    })
+}
+
+/// Parses a number literal: decimal with optional fraction and exponent,
+/// or a `0x`/`0b` integer. `_` may separate digits.
+fn parse_number(text: &str) -> Result<f64, String> {
+    let (digits, radix) = match text.get(..2) {
+        Some("0x" | "0X") => (&text[2..], 16),
+        Some("0b" | "0B") => (&text[2..], 2),
+        _ => (text, 10),
+    };
 
-                _ => {
-                    tokens.push(TokenType::Error);
-                    println!("Unexpected character: {}", c);
+    let digits = if digits.contains('_') {
+        let chars: Vec<char> = digits.chars().collect();
+        for (i, c) in chars.iter().enumerate() {
+            if *c == '_' {
+                let between_digits = i > 0
+                    && chars[i - 1].is_digit(radix)
+                    && chars.get(i + 1).is_some_and(|c| c.is_digit(radix));
+                if !between_digits {
+                    return Err("'_' must separate two digits".to_string());
                 }
             }
         }
-        tokens.push(TokenType::Eof);
-        tokens
+        Cow::Owned(chars.into_iter().filter(|c| *c != '_').collect())
+    } else {
+        Cow::Borrowed(digits)
+    };
+
+    if radix != 10 {
+        if digits.is_empty() {
+            return Err(format!("expected digits after '{}'", &text[..2]));
+        }
+        if let Some(c) = digits.chars().find(|c| !c.is_digit(radix)) {
+            return Err(format!("invalid digit '{}' for base {}", c, radix));
+        }
+        return u64::from_str_radix(&digits, radix)
+            .map(|n| n as f64)
+            .map_err(|_| "integer is too large".to_string());
+    }
+
+    let exponent = digits.find(['e', 'E']);
+    let mantissa = &digits[..exponent.unwrap_or(digits.len())];
+    if let Some(c) = mantissa.chars().find(|c| !c.is_ascii_digit() && *c != '.') {
+        return Err(format!("unexpected '{}'", c));
+    }
+    if let Some(e) = exponent {
+        let power = digits[e + 1..].trim_start_matches(['+', '-']);
+        if power.is_empty() || !power.chars().all(|c| c.is_ascii_digit()) {
+            return Err("malformed exponent".to_string());
+        }
+    }
+    digits.parse::<f64>().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+fn scan(source: &str) -> Vec<TokenType<'_>> {
+    Scanner::new(source).map(|t| t.kind).collect()
+}
+
+#[test]
+fn test_token_spans() {
+    let source = "var a = 1;\n  print \"h\u{e9}\" + a;";
+    let tokens = Scanner::new(source).scan_tokens();
+
+    let positions: Vec<(usize, usize)> = tokens
+        .iter()
+        .map(|t| (t.span.line, t.span.column))
+        .collect();
+    assert_eq!(
+        positions,
+        vec![
+            (1, 1), (1, 5), (1, 7), (1, 9), (1, 10),
+            (2, 3), (2, 9), (2, 14), (2, 16), (2, 17),
+            (2, 18),
+        ]
+    );
+
+    let lexemes: Vec<&str> = tokens
+        .iter()
+        .map(|t| &source[t.span.start..t.span.end])
+        .collect();
+    assert_eq!(
+        lexemes,
+        vec!["var", "a", "=", "1", ";", "print", "\"h\u{e9}\"", "+", "a", ";", ""]
+    );
+}
+
+#[test]
+fn test_number_literals() {
+    let number = |source: &str| match scan(source).as_slice() {
+        [TokenType::Number(n), TokenType::Eof] => *n,
+        other => panic!("expected a single number from {:?}, got {:?}", source, other),
+    };
+
+    assert_eq!(number("2.75"), 2.75);
+    assert_eq!(number("1e3"), 1000.0);
+    assert_eq!(number("2.5E-2"), 0.025);
+    assert_eq!(number("1e+2"), 100.0);
+    assert_eq!(number("1_000_000"), 1_000_000.0);
+    assert_eq!(number("0.000_1"), 0.0001);
+    assert_eq!(number("0xff"), 255.0);
+    assert_eq!(number("0XdEaD_bEeF"), 3735928559.0);
+    assert_eq!(number("0b1010"), 10.0);
+    assert_eq!(number("0b1111_0000"), 240.0);
+    assert_eq!(number("007"), 7.0);
+
+    // a trailing or doubled dot isn't part of the literal
+    assert_eq!(
+        scan("1.foo"),
+        vec![TokenType::Number(1.0), TokenType::Dot, TokenType::Identifier("foo"), TokenType::Eof]
+    );
+    assert_eq!(
+        scan("1..2"),
+        vec![TokenType::Number(1.0), TokenType::Dot, TokenType::Dot, TokenType::Number(2.0), TokenType::Eof]
+    );
+    assert_eq!(
+        scan("1.5.2"),
+        vec![TokenType::Number(1.5), TokenType::Dot, TokenType::Number(2.0), TokenType::Eof]
+    );
+    assert_eq!(
+        scan("0x1e-1"),
+        vec![TokenType::Number(30.0), TokenType::Minus, TokenType::Number(1.0), TokenType::Eof]
+    );
+
+    for malformed in ["1_", "1__0", "1_.5", "1e", "1e+", "1e_5", "1abc", "0x", "0b", "0b102", "0xfg", "0x_1", "0x1_0000_0000_0000_0000"] {
+        match scan(malformed).as_slice() {
+            [TokenType::Error(CompileErrorKind::InvalidNumber, _), TokenType::Eof] => {}
+            other => panic!("expected {:?} to be rejected, got {:?}", malformed, other),
+        }
+    }
+}
+
+#[test]
+fn test_string_escapes() {
+    assert_eq!(
+        scan(r#""a\n\t\r\0\"\\\$b""#),
+        vec![TokenType::String("a\n\t\r\0\"\\$b".into()), TokenType::Eof]
+    );
+    assert_eq!(
+        scan(r#""\u{e9}\u{1F600}""#),
+        vec![TokenType::String("\u{e9}\u{1F600}".into()), TokenType::Eof]
+    );
+    assert_eq!(
+        scan("\"two\nlines\""),
+        vec![TokenType::String("two\nlines".into()), TokenType::Eof]
+    );
+
+    for invalid in [r#""\q""#, r#""\é""#, r#""\u{}""#, r#""\u{1234567}""#, r#""\u{110000}""#, r#""\u{zz}""#] {
+        match scan(invalid).as_slice() {
+            [TokenType::Error(CompileErrorKind::InvalidEscape, _), TokenType::Eof] => {}
+            other => panic!("expected {} to be rejected, got {:?}", invalid, other),
+        }
+    }
+    assert!(matches!(
+        scan(r#""abc\"#).as_slice(),
+        [TokenType::Error(CompileErrorKind::UnterminatedString, _), TokenType::Eof]
+    ));
+}
+
+#[test]
+fn test_string_interpolation() {
+    assert_eq!(
+        scan(r#""a ${x} b ${ f({}) } c""#),
+        vec![
+            TokenType::Interpolation("a ".into()),
+            TokenType::Identifier("x"),
+            TokenType::Interpolation(" b ".into()),
+            TokenType::Identifier("f"),
+            TokenType::LeftParen,
+            TokenType::LeftBrace,
+            TokenType::RightBrace,
+            TokenType::RightParen,
+            TokenType::String(" c".into()),
+            TokenType::Eof,
+        ]
+    );
+    assert_eq!(
+        scan(r#""${"${1}"}""#),
+        vec![
+            TokenType::Interpolation("".into()),
+            TokenType::Interpolation("".into()),
+            TokenType::Number(1.0),
+            TokenType::String("".into()),
+            TokenType::String("".into()),
+            TokenType::Eof,
+        ]
+    );
+    assert_eq!(
+        scan(r#""$x \${y}""#),
+        vec![TokenType::String("$x ${y}".into()), TokenType::Eof]
+    );
+}
+
+#[test]
+fn test_keywords_need_the_whole_word() {
+    let identifier = |iden| vec![TokenType::Identifier(iden), TokenType::Eof];
+
+    let tricky = [
+        "android", "variable", "classy", "classes", "orchid", "order", "iffy", "if_",
+        "format", "fork", "fun_", "funny", "nil2", "nile", "printer", "print_", "returned",
+        "superb", "thistle", "these", "th", "truest", "true_", "falsey", "elsewhere",
+        "whiles", "breaks", "continued", "an", "cl", "f", "fa", "fo", "o", "v", "va", "w",
+        "_and", "And", "AND", "Var", "iF", "_", "__init__", "x1", "for2",
+    ];
+    for iden in tricky {
+        assert_eq!(scan(iden), identifier(iden), "{:?}", iden);
+    }
+
+    let keywords = [
+        ("and", TokenType::And), ("break", TokenType::Break), ("class", TokenType::Class),
+        ("continue", TokenType::Continue), ("else", TokenType::Else), ("false", TokenType::False),
+        ("for", TokenType::For), ("fun", TokenType::Fun), ("if", TokenType::If),
+        ("nil", TokenType::Nil), ("or", TokenType::Or), ("print", TokenType::Print),
+        ("return", TokenType::Return), ("super", TokenType::Super), ("this", TokenType::This),
+        ("true", TokenType::True), ("var", TokenType::Var), ("while", TokenType::While),
+    ];
+    for (source, kind) in keywords {
+        assert_eq!(scan(source), vec![kind, TokenType::Eof], "{:?}", source);
     }
+
+    // keywords still end at punctuation and whitespace
+    assert_eq!(
+        scan("this.and(or)"),
+        vec![
+            TokenType::This,
+            TokenType::Dot,
+            TokenType::And,
+            TokenType::LeftParen,
+            TokenType::Or,
+            TokenType::RightParen,
+            TokenType::Eof,
+        ]
+    );
+    assert_eq!(
+        scan("var variable=android;"),
+        vec![
+            TokenType::Var,
+            TokenType::Identifier("variable"),
+            TokenType::Equal,
+            TokenType::Identifier("android"),
+            TokenType::Semicolon,
+            TokenType::Eof,
+        ]
+    );
+}
+
+#[test]
+fn test_comments() {
+    assert_eq!(
+        scan("1 /* a /* nested */ still a comment */ 2 // line\n3"),
+        vec![TokenType::Number(1.0), TokenType::Number(2.0), TokenType::Number(3.0), TokenType::Eof]
+    );
+    assert_eq!(scan("/**/ 1 /*/ */"), vec![TokenType::Number(1.0), TokenType::Eof]);
+    assert_eq!(
+        scan("1 / 2 */ 3"),
+        vec![
+            TokenType::Number(1.0),
+            TokenType::Slash,
+            TokenType::Number(2.0),
+            TokenType::Star,
+            TokenType::Slash,
+            TokenType::Number(3.0),
+            TokenType::Eof,
+        ]
+    );
+    for unterminated in ["/*", "/* /* */", "1 /* 2 *"] {
+        assert!(
+            scan(unterminated).iter().any(|t| matches!(t, TokenType::Error(CompileErrorKind::UnterminatedComment, _))),
+            "{:?}",
+            unterminated
+        );
+    }
+
+    assert_eq!(
+        scan("/// Adds.\n///   indented\n///\n//// not a doc\n// nor this\nfun"),
+        vec![
+            TokenType::DocComment("Adds."),
+            TokenType::DocComment("  indented"),
+            TokenType::DocComment(""),
+            TokenType::Fun,
+            TokenType::Eof,
+        ]
+    );
+
+    // line numbers keep counting inside block comments
+    let tokens = Scanner::new("/*\n\n*/ a").scan_tokens();
+    assert_eq!((tokens[0].span.line, tokens[0].span.column), (3, 4));
+}
+
+#[test]
+fn test_scanning_is_lazy_and_borrows() {
+    let source = "foo \"plain\" \"esc\\n\" /// doc\n@";
+    let mut scanner = Scanner::new(source);
+
+    let Some(Token { kind: TokenType::Identifier(iden), .. }) = scanner.next() else {
+        panic!("expected an identifier");
+    };
+    assert!(std::ptr::eq(iden.as_ptr(), source.as_ptr()));
+    assert!(matches!(scanner.next().unwrap().kind, TokenType::String(Cow::Borrowed("plain"))));
+    assert!(matches!(scanner.next().unwrap().kind, TokenType::String(Cow::Owned(_))));
+    assert!(matches!(scanner.next().unwrap().kind, TokenType::DocComment("doc")));
+
+    // nothing past the current token has been looked at yet
+    assert!(matches!(scanner.next().unwrap().kind, TokenType::Error(CompileErrorKind::UnexpectedCharacter, _)));
+    assert!(matches!(scanner.next().unwrap().kind, TokenType::Eof));
+    assert!(scanner.next().is_none());
 }
@@ -1,8 +1,11 @@
+use std::rc::Rc;
+
 use crate::value::Value;
 
 use anyhow::Result;
 
 const MAX_LOADF: f64 = 0.75;
+const MIN_CAPACITY: usize = 8;
 
 
 use Entry::*;
@@ -10,103 +13,147 @@ use Entry::*;
 enum Entry {
     Empty,
     Tomb,
-    Full(String, Value),
+    Full(Rc<str>, Value),
 }
 
+/// Open-addressing hash table with linear probing, keyed by strings.
+/// Deleted entries leave tombstones behind so probe sequences stay intact.
 #[derive(Debug)]
 pub struct SymTable {
     table: Vec<Entry>,
+    /// Number of `Full` entries.
     size: usize,
+    /// Number of `Full` and `Tomb` entries; probing ends only at `Empty`,
+    /// so this is what the load factor is measured against.
+    used: usize,
+}
+
+impl Default for SymTable {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-#[allow(dead_code)]
 impl SymTable {
     pub fn new() -> Self {
         SymTable {
-            table: vec![Empty; 8],
+            table: vec![Empty; MIN_CAPACITY],
             size: 0,
+            used: 0,
         }
     }
 
-    pub fn set(&mut self, key: String, value: Value) -> bool {
+    /// Inserts or replaces `key`. Returns true if the key was already present.
+    pub fn set(&mut self, key: Rc<str>, value: Value) -> bool {
+        self.check_resize();
         let (index, has_item) = Self::find_entry(&self.table, &key);
         if !has_item {
             self.size += 1;
+            if let Empty = self.table[index] {
+                self.used += 1;
+            }
         }
         self.table[index] = Full(key, value);
         has_item
     }
 
-    pub fn get(&mut self, key: String) -> Option<Value> {
+    pub fn get(&self, key: &str) -> Option<&Value> {
         if self.size == 0 {
             return None;
         }
 
-        let (index, has_item) = Self::find_entry(&self.table, &key);
-        if !has_item {
-            return None;
+        match Self::find_entry(&self.table, key) {
+            (index, true) => match &self.table[index] {
+                Full(_, v) => Some(v),
+                _ => unreachable!(),
+            },
+            _ => None,
         }
-
-        if let Full(_, v) = &self.table[index] {
-            return Some(v.clone());
-        }
-        unreachable!()
     }
 
-    pub fn delete(&mut self, key: &String) -> Result<()> {
+    pub fn delete(&mut self, key: &str) -> Result<()> {
         if self.size == 0 {
             anyhow::bail!("table is empty");
         }
-        let (index, has_item) = Self::find_entry(&self.table, &key);
+        let (index, has_item) = Self::find_entry(&self.table, key);
         if !has_item {
             anyhow::bail!("key not found");
         }
         self.table[index] = Tomb;
+        self.size -= 1;
         Ok(())
     }
 
+    pub fn len(&self) -> usize {
+        self.size
+    }
 
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Iterates over the entries in table order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Rc<str>, &Value)> {
+        self.table.iter().filter_map(|entry| match entry {
+            Full(k, v) => Some((k, v)),
+            _ => None,
+        })
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Rc<str>> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// Copies every entry into `to`, overwriting keys it already has.
+    pub fn add_all(&self, to: &mut SymTable) {
+        for (k, v) in self.iter() {
+            to.set(Rc::clone(k), v.clone());
+        }
+    }
+
+    /// Makes room for one more entry. Rehashing drops tombstones, so a
+    /// table that's mostly tombstones is cleaned up rather than grown.
     fn check_resize(&mut self) {
-        if (self.size as f64) < (self.table.len() as f64 * MAX_LOADF) {
+        if ((self.used + 1) as f64) <= (self.table.len() as f64 * MAX_LOADF) {
             return
         }
 
-        let new_capacity = self.table.len() * 2;
+        let mut new_capacity = self.table.len();
+        while ((self.size + 1) as f64) > (new_capacity as f64 * MAX_LOADF / 2.0) {
+            new_capacity *= 2;
+        }
         let mut new_table: Vec<Entry> = vec![Empty; new_capacity];
 
-        let old_elems = std::mem::take(&mut self.table)
-            .into_iter()
-            .filter_map(|e| {
-                if let Full(k, v) = e {
-                    Some((k, v))
-                } else {
-                    None
-                }
-            });
-
-        self.size = 0;
-        for (k, v) in old_elems {
-            let (index, _) = Self::find_entry(&new_table, &k);
-            new_table[index] = Full(k, v);
-            self.size += 1;
+        for entry in std::mem::take(&mut self.table) {
+            if let Full(k, v) = entry {
+                let (index, _) = Self::find_entry(&new_table, &k);
+                new_table[index] = Full(k, v);
+            }
         }
+        self.used = self.size;
         self.table = new_table;
     }
 
-    fn find_entry(v: &Vec<Entry>, key: &str) -> (usize, bool) {
+    /// Returns the index holding `key` and true, or the index where `key`
+    /// should be inserted and false. Insertion reuses the first tombstone
+    /// on the probe sequence.
+    fn find_entry(v: &[Entry], key: &str) -> (usize, bool) {
         let mut index = Self::hash_key(key) as usize % v.len();
+        let mut tomb = None;
         loop {
             match &v[index] {
-                Full(k, _) if k == key => {
+                Full(k, _) if &**k == key => {
                     return (index, true);
                 }
                 Empty => {
-                    return (index, false);
+                    return (tomb.unwrap_or(index), false);
                 }
-                Tomb | Full(..) => {
-                    index = (index + 1) % v.len();
+                Tomb => {
+                    tomb.get_or_insert(index);
                 }
+                Full(..) => {}
             }
+            index = (index + 1) % v.len();
         }
     }
 
@@ -123,6 +170,18 @@ impl SymTable {
 #[test]
 fn test_size() {
     println!("size of symtable: {}", std::mem::size_of::<SymTable>());
-    println!("size of <Option<(String, Value)>>: {}", std::mem::size_of::<Option<(String, Value)>>());
+    println!("size of <Option<(Rc<str>, Value)>>: {}", std::mem::size_of::<Option<(Rc<str>, Value)>>());
 }
 
+#[test]
+fn test_tombstones_do_not_fill_the_table() {
+    let mut table = SymTable::new();
+    for i in 0..1000 {
+        let key: Rc<str> = Rc::from(format!("k{}", i));
+        table.set(Rc::clone(&key), Value::Number(i as f64));
+        table.delete(&key).unwrap();
+    }
+    assert!(table.is_empty());
+    assert_eq!(table.table.len(), MIN_CAPACITY);
+    assert!(table.get("k999").is_none());
+}
@@ -1,5 +1,28 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+
+use crate::error::CompileErrorKind;
+
+/// Location of a token in the source. `line` and `column` are 1-based and
+/// point at the first character; `start..end` is the byte range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
 #[derive(Debug, Clone)]
-pub enum TokenType {
+pub struct Token<'src> {
+    pub kind: TokenType<'src>,
+    pub span: Span,
+}
+
+/// The kind of a token. Lexemes borrow from the source unless the scanner
+/// had to rewrite them, e.g. to process string escapes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenType<'src> {
     // Single-character tokens.
     LeftParen,
     RightParen,
@@ -22,12 +45,20 @@ pub enum TokenType {
     Less,
     LessEqual,
     //Literals.
-    Identifier(String),
-    String(String),
+    Identifier(&'src str),
+    String(Cow<'src, str>),
+    /// A string segment followed by `${`; the interpolated expression's
+    /// tokens come next, then the rest of the string.
+    Interpolation(Cow<'src, str>),
     Number(f64),
+    // Trivia.
+    /// The text of a `///` comment, without the slashes.
+    DocComment(&'src str),
     //Keywords.
     And,
+    Break,
     Class,
+    Continue,
     Else,
     False,
     For,
@@ -43,6 +74,57 @@ pub enum TokenType {
     Var,
     While,
 
-    Error,
+    Error(CompileErrorKind, String),
     Eof,
 }
+
+impl Display for TokenType<'_> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            TokenType::LeftParen => write!(f, "("),
+            TokenType::RightParen => write!(f, ")"),
+            TokenType::LeftBrace => write!(f, "{{"),
+            TokenType::RightBrace => write!(f, "}}"),
+            TokenType::Comma => write!(f, ","),
+            TokenType::Dot => write!(f, "."),
+            TokenType::Minus => write!(f, "-"),
+            TokenType::Plus => write!(f, "+"),
+            TokenType::Semicolon => write!(f, ";"),
+            TokenType::Slash => write!(f, "/"),
+            TokenType::Star => write!(f, "*"),
+            TokenType::Bang => write!(f, "!"),
+            TokenType::BangEqual => write!(f, "!="),
+            TokenType::Equal => write!(f, "="),
+            TokenType::EqualEqual => write!(f, "=="),
+            TokenType::Greater => write!(f, ">"),
+            TokenType::GreaterEqual => write!(f, ">="),
+            TokenType::Less => write!(f, "<"),
+            TokenType::LessEqual => write!(f, "<="),
+            TokenType::Identifier(s) => write!(f, "{}", s),
+            TokenType::String(s) => write!(f, "\"{}\"", s),
+            TokenType::Interpolation(s) => write!(f, "\"{}${{", s),
+            TokenType::Number(n) => write!(f, "{}", n),
+            TokenType::DocComment(s) => write!(f, "///{}", s),
+            TokenType::And => write!(f, "and"),
+            TokenType::Break => write!(f, "break"),
+            TokenType::Class => write!(f, "class"),
+            TokenType::Continue => write!(f, "continue"),
+            TokenType::Else => write!(f, "else"),
+            TokenType::False => write!(f, "false"),
+            TokenType::For => write!(f, "for"),
+            TokenType::Fun => write!(f, "fun"),
+            TokenType::If => write!(f, "if"),
+            TokenType::Nil => write!(f, "nil"),
+            TokenType::Or => write!(f, "or"),
+            TokenType::Print => write!(f, "print"),
+            TokenType::Return => write!(f, "return"),
+            TokenType::Super => write!(f, "super"),
+            TokenType::This => write!(f, "this"),
+            TokenType::True => write!(f, "true"),
+            TokenType::Var => write!(f, "var"),
+            TokenType::While => write!(f, "while"),
+            TokenType::Error(_, msg) => write!(f, "{}", msg),
+            TokenType::Eof => write!(f, "end"),
+        }
+    }
+}
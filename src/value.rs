@@ -1,20 +1,59 @@
-use std::fmt::{Display, Formatter};
+use std::rc::Rc;
 
+use crate::heap::Handle;
+use crate::object::{Function, NativeFn};
+
+/// A Lox value. Objects that can refer to other objects live on the VM's
+/// `Heap` and are referenced by handle, so formatting a value needs the heap
+/// too: see `Heap::display`.
 #[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
     Bool(bool),
-    String(String),
+    String(Rc<str>),
+    Function(Rc<Function>),
+    NativeFn(Rc<NativeFn>),
+    Closure(Handle),
+    Class(Handle),
+    Instance(Handle),
+    BoundMethod(Handle),
     Nil,
 }
 
-impl Display for Value {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+impl Value {
+    /// The heap object this value refers to, if any.
+    pub fn handle(&self) -> Option<Handle> {
         match self {
-            Value::Number(n) => write!(f, "{}", n),
-            Value::Bool(b) => write!(f, "{}", b),
-            Value::String(s) => write!(f, "{}", s),
-            Value::Nil => write!(f, "nil"),
+            Value::Closure(handle)
+            | Value::Class(handle)
+            | Value::Instance(handle)
+            | Value::BoundMethod(handle) => Some(*handle),
+            _ => None,
+        }
+    }
+
+    /// `nil` and `false` are falsey, everything else is truthy.
+    pub fn is_falsey(&self) -> bool {
+        matches!(self, Value::Nil | Value::Bool(false))
+    }
+}
+
+/// Lox equality: values of different types are never equal, strings compare
+/// by content and objects by identity.
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Nil, Value::Nil) => true,
+            (Value::String(a), Value::String(b)) => Rc::ptr_eq(a, b) || a == b,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::NativeFn(a), Value::NativeFn(b)) => Rc::ptr_eq(a, b),
+            (Value::Closure(a), Value::Closure(b))
+            | (Value::Class(a), Value::Class(b))
+            | (Value::Instance(a), Value::Instance(b))
+            | (Value::BoundMethod(a), Value::BoundMethod(b)) => a == b,
+            _ => false,
         }
     }
 }
@@ -27,7 +66,49 @@ fn test_size() {
     println!("size of String: {}", std::mem::size_of::<String>());
     println!("size of Value::Number: {}", std::mem::size_of_val(&Value::Number(123.0)));
     println!("size of Value::Bool: {}", std::mem::size_of_val(&Value::Bool(true)));
-    println!("size of Value::String: {}", std::mem::size_of_val(&Value::String("hello".to_string())));
+    println!("size of Value::String: {}", std::mem::size_of_val(&Value::String("hello".into())));
     println!("size of Value::Nil: {}", std::mem::size_of_val(&Value::Nil));
-    assert_eq!(std::mem::size_of::<Value>(), 32);
+    // The largest payload is an `Rc<str>` fat pointer (16 bytes) and the tag
+    // fits in the remaining word. Every stack slot and constant is a `Value`,
+    // so growing it is a deliberate decision: update this along with it.
+    assert_eq!(std::mem::size_of::<Value>(), 24);
+}
+
+#[test]
+fn test_truthiness() {
+    assert!(Value::Nil.is_falsey());
+    assert!(Value::Bool(false).is_falsey());
+    for value in [Value::Bool(true), Value::Number(0.0), Value::String("".into())] {
+        assert!(!value.is_falsey(), "{:?}", value);
+    }
+}
+
+#[test]
+fn test_equality() {
+    use crate::heap::{Heap, Obj};
+    use crate::object::Class;
+
+    assert_eq!(Value::Nil, Value::Nil);
+    assert_eq!(Value::Number(1.0), Value::Number(1.0));
+    assert_ne!(Value::Number(f64::NAN), Value::Number(f64::NAN));
+    assert_eq!(Value::String("ab".into()), Value::String("ab".into()));
+    assert_ne!(Value::String("ab".into()), Value::String("ba".into()));
+
+    // never equal across types
+    assert_ne!(Value::Nil, Value::Bool(false));
+    assert_ne!(Value::Bool(true), Value::Number(1.0));
+    assert_ne!(Value::Number(0.0), Value::Bool(false));
+    assert_ne!(Value::String("1".into()), Value::Number(1.0));
+    assert_ne!(Value::String("nil".into()), Value::Nil);
+
+    // functions and objects by identity
+    let function = Rc::new(Function::new("f"));
+    assert_eq!(Value::Function(function.clone()), Value::Function(function));
+    assert_ne!(Value::Function(Rc::new(Function::new("f"))), Value::Function(Rc::new(Function::new("f"))));
+    let mut heap = Heap::new();
+    let a = heap.alloc(Obj::Class(Class::new("A")));
+    let b = heap.alloc(Obj::Class(Class::new("A")));
+    assert_eq!(Value::Class(a), Value::Class(a));
+    assert_ne!(Value::Class(a), Value::Class(b));
+    assert_ne!(Value::Class(a), Value::Instance(a));
 }
@@ -1,209 +1,663 @@
+use std::rc::Rc;
+
 use crate::chunk::Chunk;
+use crate::error::{CompileError, RuntimeError, StackFrame};
+use crate::globals::Globals;
+use crate::heap::{Displayed, Handle, Heap, Obj};
+use crate::interner::Interner;
+use crate::natives;
+use crate::object::{BoundMethod, Class, Closure, Function, Instance, NativeFn, Upvalue};
 use crate::op::Op;
+use crate::parser;
 use crate::value::Value;
-use crate::symtable::SymTable;
+
+const FRAMES_MAX: usize = 64;
 
 pub struct VM {
-    pub chunk: Chunk,
+    frames: Vec<CallFrame>,
     stack: Vec<Value>,
-    symtable: SymTable,
+    globals: Globals,
+    strings: Interner,
+    heap: Heap,
+    // upvalues still pointing into the stack, sorted by slot
+    open_upvalues: Vec<Handle>,
+}
+
+struct CallFrame {
+    closure: Handle,
+    // the closure's function, kept here so fetching code skips the heap
+    function: Rc<Function>,
     ip: usize,
+    // index of the stack slot holding the callee; locals follow it
+    slots: usize,
 }
 
-enum InterpretResult {
-    InterpretOk,
-    CompileError(String),
-    RuntimeError(String),
+pub enum InterpretResult {
+    InterpretOk(Value),
+    CompileError(Vec<CompileError>),
+    RuntimeError(RuntimeError),
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl VM {
     pub fn new() -> VM {
-        VM {
-            chunk: Chunk::new(),
+        let mut vm = VM {
+            frames: Vec::new(),
             stack: Vec::new(),
-            symtable: SymTable::new(),
-            ip: 0,
+            globals: Globals::new(),
+            strings: Interner::new(),
+            heap: Heap::new(),
+            open_upvalues: Vec::new(),
+        };
+        natives::define(&mut vm);
+        vm
+    }
+
+    pub fn interpret(&mut self, source: &str) -> InterpretResult {
+        match self.compile(source) {
+            Ok(chunk) => self.execute(chunk),
+            Err(errors) => InterpretResult::CompileError(errors),
         }
     }
-    pub fn interpret(&mut self) {
+
+    /// Compiles `source` against this VM's globals and strings. Only chunks
+    /// compiled this way may be passed to `execute`.
+    pub fn compile(&mut self, source: &str) -> Result<Chunk, Vec<CompileError>> {
+        parser::compile_with(source, &mut self.globals, &mut self.strings)
+    }
+
+    pub fn execute(&mut self, chunk: Chunk) -> InterpretResult {
+        let mut function = Function::new("");
+        function.chunk = chunk;
+        let function = Rc::new(function);
+        self.stack.clear();
+        self.frames.clear();
+        self.open_upvalues.clear();
+        let script = self.heap.alloc(Obj::Closure(Closure {
+            function: Rc::clone(&function),
+            upvalues: Vec::new(),
+        }));
+        self.stack.push(Value::Closure(script));
+        self.frames.push(CallFrame {
+            closure: script,
+            function,
+            ip: 0,
+            slots: 0,
+        });
+
         match self.run() {
-            InterpretResult::InterpretOk => {}
-            InterpretResult::CompileError(e) => {
-                println!("Compile error: {}", e);
+            Ok(value) => InterpretResult::InterpretOk(value),
+            Err(message) => InterpretResult::RuntimeError(self.runtime_error(message)),
+        }
+    }
+
+    /// Locates the instruction that just failed and captures the stack.
+    fn runtime_error(&self, message: String) -> RuntimeError {
+        let chunk = &self.frame().function.chunk;
+        let span = chunk.span(self.frame().ip.saturating_sub(1));
+        let source_line = chunk.source
+            .lines()
+            .nth(span.line.saturating_sub(1))
+            .unwrap_or_default()
+            .to_string();
+        let trace = self.frames
+            .iter()
+            .rev()
+            .map(|frame| StackFrame {
+                function: frame.function.name.clone(),
+                line: frame.function.chunk.line(frame.ip.saturating_sub(1)),
+            })
+            .collect();
+        RuntimeError { message, span, trace, source_line }
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.globals.lookup(name).cloned()
+    }
+
+    /// Defines or redefines the global `name`. Returns false, leaving the
+    /// globals unchanged, if `name` is new and every global slot is taken,
+    /// or if `value` refers to an object that has been collected.
+    pub fn set_global(&mut self, name: &str, value: Value) -> bool {
+        if value.handle().is_some_and(|handle| !self.heap.contains(handle)) {
+            return false;
+        }
+        let name = self.strings.intern(name);
+        match self.globals.resolve(&name) {
+            Some(slot) => self.globals.define(slot as usize, value),
+            None => false,
+        }
+    }
+
+    /// Defines a global function `name` implemented in Rust. Calls with other
+    /// than `arity` arguments fail before `function` runs. Returns false if
+    /// the global couldn't be defined, see `set_global`.
+    pub fn define_native<F>(&mut self, name: &str, arity: usize, function: F) -> bool
+    where
+        F: Fn(&Heap, &[Value]) -> Result<Value, String> + 'static,
+    {
+        let native = NativeFn {
+            name: name.to_string(),
+            arity,
+            function: Box::new(function),
+        };
+        self.set_global(name, Value::NativeFn(Rc::new(native)))
+    }
+
+    /// Formats `value`, which may refer to objects on this VM's heap.
+    pub fn display<'a>(&'a self, value: &'a Value) -> Displayed<'a> {
+        self.heap.display(value)
+    }
+
+    pub fn heap(&self) -> &Heap {
+        &self.heap
+    }
+
+    /// The heap, e.g. to tune when it collects garbage.
+    pub fn heap_mut(&mut self) -> &mut Heap {
+        &mut self.heap
+    }
+
+    /// Frees every object that can't be reached from the stack, the globals
+    /// or the call frames. Handles held outside the VM aren't roots.
+    pub fn collect_garbage(&mut self) {
+        for value in &self.stack {
+            self.heap.mark_value(value);
+        }
+        for value in self.globals.values() {
+            self.heap.mark_value(value);
+        }
+        for frame in &self.frames {
+            self.heap.mark(frame.closure);
+        }
+        for upvalue in &self.open_upvalues {
+            self.heap.mark(*upvalue);
+        }
+        self.heap.collect();
+    }
+
+    fn undefined_variable(&self, slot: usize) -> String {
+        format!("Undefined variable '{}'.", self.globals.name(slot).unwrap_or("?"))
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().expect("no active call frame")
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("no active call frame")
+    }
+
+    fn call_value(&mut self, callee: Value, arg_count: usize) -> Result<(), String> {
+        let callee_slot = self.stack.len() - arg_count - 1;
+        match callee {
+            Value::Closure(closure) => self.call(closure, arg_count),
+            Value::NativeFn(native) => {
+                if arg_count != native.arity {
+                    return Err(format!(
+                        "Expected {} arguments but got {}.",
+                        native.arity, arg_count
+                    ));
+                }
+                let result = (native.function)(&self.heap, &self.stack[callee_slot + 1..])?;
+                self.stack.truncate(callee_slot);
+                self.stack.push(result);
+                Ok(())
             }
-            InterpretResult::RuntimeError(e) => {
-                println!("Runtime error: {}", e);
+            Value::BoundMethod(bound) => {
+                let bound = self.heap.bound_method(bound);
+                let method = bound.method;
+                self.stack[callee_slot] = bound.receiver.clone();
+                self.call(method, arg_count)
             }
+            Value::Class(class) => {
+                let instance = self.heap.alloc(Obj::Instance(Instance::new(class)));
+                self.stack[callee_slot] = Value::Instance(instance);
+                let initializer = self.heap.class(class).methods.get("init").cloned();
+                match initializer {
+                    Some(Value::Closure(initializer)) => self.call(initializer, arg_count),
+                    _ if arg_count != 0 => {
+                        Err(format!("Expected 0 arguments but got {}.", arg_count))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            _ => Err("Can only call functions and classes.".to_string()),
         }
     }
 
-    fn run(&mut self) -> InterpretResult {
-        println!("\nRunning...");
+    /// Replaces the instance on top of the stack with `name` bound to it.
+    fn bind_method(&mut self, class: Handle, name: &str) -> Result<(), String> {
+        let method = match self.heap.class(class).methods.get(name) {
+            Some(Value::Closure(method)) => *method,
+            _ => return Err(format!("Undefined property '{}'.", name)),
+        };
+        let receiver = self.pop()?;
+        let bound = self.heap.alloc(Obj::BoundMethod(BoundMethod { receiver, method }));
+        self.stack.push(Value::BoundMethod(bound));
+        Ok(())
+    }
+
+    fn call(&mut self, closure: Handle, arg_count: usize) -> Result<(), String> {
+        let function = Rc::clone(&self.heap.closure(closure).function);
+        if arg_count != function.arity {
+            return Err(format!(
+                "Expected {} arguments but got {}.",
+                function.arity, arg_count
+            ));
+        }
+        if self.frames.len() == FRAMES_MAX {
+            return Err("Stack overflow.".to_string());
+        }
+        self.frames.push(CallFrame {
+            closure,
+            function,
+            ip: 0,
+            slots: self.stack.len() - arg_count - 1,
+        });
+        Ok(())
+    }
+
+    /// Returns the open upvalue for `slot`, creating it if no closure has
+    /// captured that variable yet, so closures share captured variables.
+    fn capture_upvalue(&mut self, slot: usize) -> Handle {
+        let mut insert_at = self.open_upvalues.len();
+        for (i, upvalue) in self.open_upvalues.iter().enumerate() {
+            if let Upvalue::Open(open_slot) = *self.heap.upvalue(*upvalue) {
+                if open_slot == slot {
+                    return *upvalue;
+                }
+                if open_slot > slot {
+                    insert_at = i;
+                    break;
+                }
+            }
+        }
+
+        let upvalue = self.heap.alloc(Obj::Upvalue(Upvalue::Open(slot)));
+        self.open_upvalues.insert(insert_at, upvalue);
+        upvalue
+    }
+
+    /// Moves every captured variable living at `last_slot` or above off the
+    /// stack and into its upvalue.
+    fn close_upvalues(&mut self, last_slot: usize) {
+        while let Some(&upvalue) = self.open_upvalues.last() {
+            let Upvalue::Open(slot) = *self.heap.upvalue(upvalue) else {
+                unreachable!();
+            };
+            if slot < last_slot {
+                break;
+            }
+            let value = self.stack.get(slot).cloned().unwrap_or(Value::Nil);
+            *self.heap.upvalue_mut(upvalue) = Upvalue::Closed(value);
+            self.open_upvalues.pop();
+        }
+    }
+
+    fn read_upvalue(&self, upvalue: Handle) -> Result<Value, String> {
+        match self.heap.upvalue(upvalue) {
+            Upvalue::Open(slot) => self.stack
+                .get(*slot)
+                .cloned()
+                .ok_or_else(|| format!("invalid upvalue slot {}", slot)),
+            Upvalue::Closed(value) => Ok(value.clone()),
+        }
+    }
+
+    fn pop(&mut self) -> Result<Value, String> {
+        self.stack.pop().ok_or_else(|| "stack is empty".to_string())
+    }
+
+    fn peek(&self) -> Result<&Value, String> {
+        self.stack.last().ok_or_else(|| "stack is empty".to_string())
+    }
+
+    fn pop_pair(&mut self) -> Result<(Value, Value), String> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        Ok((a, b))
+    }
+
+    fn run(&mut self) -> Result<Value, String> {
+        // cached copies of the current frame's closure, code and stack window
+        let mut closure = self.frame().closure;
+        let mut function = Rc::clone(&self.frame().function);
+        let mut slots = self.frame().slots;
+
+        macro_rules! read_byte {
+            () => {{
+                let frame = self.frames.last_mut().expect("no active call frame");
+                let byte = function.chunk.code.get(frame.ip).copied();
+                frame.ip += 1;
+                byte.ok_or("unexpected end of bytecode")?
+            }};
+        }
+
+        macro_rules! read_u16 {
+            () => {{
+                let high = read_byte!() as usize;
+                (high << 8) | read_byte!() as usize
+            }};
+        }
+
+        macro_rules! read_constant {
+            ($index:expr) => {
+                function.chunk.constants
+                    .get($index)
+                    .ok_or_else(|| format!("invalid constant index {}", $index))?
+            };
+        }
+
+        macro_rules! read_name {
+            () => {{
+                let index = read_u16!();
+                match read_constant!(index) {
+                    Value::String(name) => name,
+                    _ => return Err(format!("constant {} is not a name", index)),
+                }
+            }};
+        }
+
         loop {
-            let op = &self.chunk.code[self.ip];
+            // between instructions everything live is reachable from a root
+            if self.heap.should_collect() {
+                self.collect_garbage();
+            }
+
+            let ip = self.frame().ip;
+            let Some(&byte) = function.chunk.code.get(ip) else {
+                return Ok(Value::Nil);
+            };
+            self.frame_mut().ip += 1;
+            let op = Op::from_byte(byte).ok_or_else(|| format!("unknown opcode {}", byte))?;
+
             match op {
-                Op::Nop => {
-                    return InterpretResult::InterpretOk;
+                Op::Call => {
+                    let arg_count = read_byte!() as usize;
+                    let callee = self.stack
+                        .len()
+                        .checked_sub(arg_count + 1)
+                        .and_then(|i| self.stack.get(i))
+                        .cloned()
+                        .ok_or("stack is empty")?;
+                    self.call_value(callee, arg_count)?;
+                    closure = self.frame().closure;
+                    function = Rc::clone(&self.frame().function);
+                    slots = self.frame().slots;
+                }
+                Op::Closure => {
+                    let index = read_u16!();
+                    let Value::Function(inner) = read_constant!(index) else {
+                        return Err(format!("constant {} is not a function", index));
+                    };
+                    let inner = Rc::clone(inner);
+                    let mut upvalues = Vec::with_capacity(inner.upvalue_count);
+                    for _ in 0..inner.upvalue_count {
+                        let is_local = read_byte!() == 1;
+                        let index = read_byte!() as usize;
+                        let upvalue = if is_local {
+                            self.capture_upvalue(slots + index)
+                        } else {
+                            let upvalues = &self.heap.closure(closure).upvalues;
+                            *upvalues.get(index).ok_or("invalid upvalue index")?
+                        };
+                        upvalues.push(upvalue);
+                    }
+                    let inner = self.heap.alloc(Obj::Closure(Closure {
+                        function: inner,
+                        upvalues,
+                    }));
+                    self.stack.push(Value::Closure(inner));
+                }
+                Op::GetUpvalue => {
+                    let idx = read_byte!() as usize;
+                    let upvalues = &self.heap.closure(closure).upvalues;
+                    let upvalue = *upvalues.get(idx).ok_or("invalid upvalue index")?;
+                    let value = self.read_upvalue(upvalue)?;
+                    self.stack.push(value);
+                }
+                Op::SetUpvalue => {
+                    let idx = read_byte!() as usize;
+                    let value = self.peek()?.clone();
+                    let upvalues = &self.heap.closure(closure).upvalues;
+                    let upvalue = *upvalues.get(idx).ok_or("invalid upvalue index")?;
+                    let slot = match self.heap.upvalue_mut(upvalue) {
+                        Upvalue::Open(slot) => *slot,
+                        Upvalue::Closed(closed) => {
+                            *closed = value;
+                            continue;
+                        }
+                    };
+                    match self.stack.get_mut(slot) {
+                        Some(v) => *v = value,
+                        None => return Err(format!("invalid upvalue slot {}", slot)),
+                    }
+                }
+                Op::Class => {
+                    let name = read_name!();
+                    let class = self.heap.alloc(Obj::Class(Class::new(name)));
+                    self.stack.push(Value::Class(class));
+                }
+                Op::Method => {
+                    let name = read_name!();
+                    let method = self.pop()?;
+                    let Value::Class(class) = *self.peek()? else {
+                        return Err("methods can only be attached to classes".to_string());
+                    };
+                    self.heap.class_mut(class).methods.set(Rc::clone(name), method);
+                }
+                Op::Inherit => {
+                    let subclass = self.pop()?;
+                    let (&Value::Class(superclass), Value::Class(subclass)) = (self.peek()?, subclass) else {
+                        return Err("Superclass must be a class.".to_string());
+                    };
+                    // copy-down inheritance: methods defined in the subclass
+                    // body are added afterwards and override these. The
+                    // table is moved out so both classes can be borrowed.
+                    let mut methods = std::mem::take(&mut self.heap.class_mut(subclass).methods);
+                    self.heap.class(superclass).methods.add_all(&mut methods);
+                    self.heap.class_mut(subclass).methods = methods;
+                }
+                Op::GetSuper => {
+                    let name = read_name!();
+                    let Value::Class(superclass) = self.pop()? else {
+                        return Err("Superclass must be a class.".to_string());
+                    };
+                    self.bind_method(superclass, name)?;
+                }
+                Op::GetProperty => {
+                    let name = read_name!();
+                    let Value::Instance(instance) = *self.peek()? else {
+                        return Err("Only instances have properties.".to_string());
+                    };
+                    let field = self.heap.instance(instance).fields.get(name).cloned();
+                    match field {
+                        Some(value) => {
+                            self.pop()?;
+                            self.stack.push(value);
+                        }
+                        None => {
+                            let class = self.heap.instance(instance).class;
+                            self.bind_method(class, name)?;
+                        }
+                    }
+                }
+                Op::SetProperty => {
+                    let name = read_name!();
+                    let value = self.pop()?;
+                    let Value::Instance(instance) = self.pop()? else {
+                        return Err("Only instances have fields.".to_string());
+                    };
+                    self.heap.instance_mut(instance).fields.set(Rc::clone(name), value.clone());
+                    self.stack.push(value);
+                }
+                Op::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len().saturating_sub(1));
+                    self.pop()?;
                 }
                 Op::Return => {
-                    return InterpretResult::InterpretOk;
+                    let result = self.stack.pop().unwrap_or(Value::Nil);
+                    let finished = self.frames.pop().expect("no active call frame");
+                    self.close_upvalues(finished.slots);
+                    if self.frames.is_empty() {
+                        self.stack.clear();
+                        return Ok(result);
+                    }
+                    self.stack.truncate(finished.slots);
+                    self.stack.push(result);
+                    closure = self.frame().closure;
+                    function = Rc::clone(&self.frame().function);
+                    slots = self.frame().slots;
                 }
-                Op::JumpIfFalse(offset) => {
-                    if let Value::Bool(false) = self.stack.last().expect("stack is empty") {
-                        self.ip += offset;
+                Op::JumpIfFalse => {
+                    let offset = read_u16!();
+                    if self.peek()?.is_falsey() {
+                        self.frame_mut().ip += offset;
                     }
                 }
-                Op::JumpIfTrue(offset) => {
-                    if let Value::Bool(true) = self.stack.last().expect("stack is empty") {
-                        self.ip += offset;
+                Op::JumpIfTrue => {
+                    let offset = read_u16!();
+                    if !self.peek()?.is_falsey() {
+                        self.frame_mut().ip += offset;
                     }
                 }
-                Op::Jump(offset) => {
-                    self.ip += offset;
+                Op::Jump => {
+                    let offset = read_u16!();
+                    self.frame_mut().ip += offset;
                 }
-                Op::Loop(offset) => {
-                    self.ip -= offset;
+                Op::Loop => {
+                    let offset = read_u16!();
+                    let frame = self.frame_mut();
+                    frame.ip = frame.ip.checked_sub(offset).ok_or("loop jumps before start of chunk")?;
                 }
-                Op::GetGlobal(iden_str) => {
-                    match self.symtable.get(iden_str.clone()) {
-                        Some(v) => self.stack.push(v),
-                        None => return InterpretResult::RuntimeError(format!("undefined variable '{}'", iden_str)),
+                Op::GetGlobal => {
+                    let slot = read_u16!();
+                    match self.globals.get(slot) {
+                        Some(value) => self.stack.push(value.clone()),
+                        None => return Err(self.undefined_variable(slot)),
                     }
                 }
-                Op::SetGlobal(iden_str) => {
-                    let iden = iden_str.clone();
-                    let overwrited = self.symtable.set(
-                        iden.clone(), 
-                        self.stack.last().expect("stack is empty").clone()
-                    );
-                    if !overwrited {
-                        self.symtable.delete(iden_str).expect("can't delete");
-                        return InterpretResult::RuntimeError(format!("undefined variable '{}'", iden_str));
+                Op::SetGlobal => {
+                    let slot = read_u16!();
+                    let value = self.peek()?.clone();
+                    if !self.globals.set(slot, value) {
+                        return Err(self.undefined_variable(slot));
                     }
                 }
-                Op::SetLocal(idx) => {
-                    self.stack[*idx] = self.stack.last().expect("stack is empty").clone();
+                Op::SetLocal => {
+                    let idx = slots + read_byte!() as usize;
+                    let value = self.peek()?.clone();
+                    match self.stack.get_mut(idx) {
+                        Some(slot) => *slot = value,
+                        None => return Err(format!("invalid local slot {}", idx)),
+                    }
                 }
-                Op::GetLocal(idx) => {
-                    self.stack.push(self.stack[*idx].clone());
+                Op::GetLocal => {
+                    let idx = read_byte!() as usize;
+                    match self.stack.get(slots + idx) {
+                        Some(value) => self.stack.push(value.clone()),
+                        None => return Err(format!("invalid local slot {}", idx)),
+                    }
                 }
-                Op::DefineGlobal(iden_str) => {
-                    self.symtable.set(iden_str.clone(), self.stack.pop().expect("stack is empty"));
+                Op::DefineGlobal => {
+                    let slot = read_u16!();
+                    let value = self.pop()?;
+                    if !self.globals.define(slot, value) {
+                        return Err(format!("invalid global slot {}", slot));
+                    }
                 }
                 Op::Pop => {
-                    self.stack.pop();
+                    self.pop()?;
+                }
+                Op::Constant => {
+                    let index = read_byte!() as usize;
+                    let constant = read_constant!(index).clone();
+                    self.stack.push(constant);
                 }
-                Op::Constant(constant) => {
-                    self.stack.push(constant.clone());
+                Op::ConstantLong => {
+                    let high = read_byte!() as usize;
+                    let index = (high << 16) | read_u16!();
+                    let constant = read_constant!(index).clone();
+                    self.stack.push(constant);
                 }
+                Op::Nil => self.stack.push(Value::Nil),
+                Op::True => self.stack.push(Value::Bool(true)),
+                Op::False => self.stack.push(Value::Bool(false)),
                 Op::Negate => {
-                    let m = self.stack.last_mut();
-                    match m {
+                    match self.stack.last_mut() {
                         Some(Value::Number(v)) => *v = -*v,
-                        Some(x) => {
-                            return InterpretResult::RuntimeError(format!("can't negate {:?}", x))
-                        }
-                        None => {
-                            return InterpretResult::RuntimeError("nothing to negate".to_string());
-                        }
+                        Some(_) => return Err("Operand must be a number.".to_string()),
+                        None => return Err("nothing to negate".to_string()),
+                    }
+                }
+                Op::Stringify => {
+                    match self.stack.last_mut() {
+                        Some(Value::String(_)) => {}
+                        Some(x) => *x = Value::String(self.heap.display(x).to_string().into()),
+                        None => return Err("nothing to stringify".to_string()),
                     }
                 }
                 Op::Not => {
-                    let m = self.stack.last_mut();
-                    match m {
-                        Some(Value::Bool(v)) => *v = !*v,
-                        Some(x) => {
-                            return InterpretResult::RuntimeError(format!("can't negate {:?}", x))
-                        }
-                        None => {
-                            return InterpretResult::RuntimeError("nothing to negate".to_string());
-                        }
+                    match self.stack.last_mut() {
+                        Some(x) => *x = Value::Bool(x.is_falsey()),
+                        None => return Err("nothing to negate".to_string()),
                     }
                 }
                 Op::Add => {
-                    match (&self.stack.pop(), &self.stack.pop()) {
-                        (Some(Value::Number(a)), Some(Value::Number(b))) => self.stack.push(Value::Number(b + a)),
-                        (Some(Value::String(a)), Some(Value::String(b))) => self.stack.push(Value::String(format!("{}{}", b, a))),
-                        (a, b) => return InterpretResult::RuntimeError(
-                            format!("tried to perform binop '+' but arguments are invalid: {:?} {:?}", a, b)
-                        )
+                    match self.pop_pair()? {
+                        (Value::Number(a), Value::Number(b)) => self.stack.push(Value::Number(a + b)),
+                        (Value::String(a), Value::String(b)) => self.stack.push(Value::String(format!("{}{}", a, b).into())),
+                        _ => return Err("Operands must be two numbers or two strings.".to_string()),
                     };
                 }
                 Op::Subtract => {
-                    match (&self.stack.pop(), &self.stack.pop()) {
-                        (Some(Value::Number(a)), Some(Value::Number(b))) => self.stack.push(Value::Number(b - a)),
-                        (a, b) => return InterpretResult::RuntimeError(
-                            format!("tried to perform binop '-' but arguments are invalid: {:?} {:?}", a, b)
-                        )
+                    match self.pop_pair()? {
+                        (Value::Number(a), Value::Number(b)) => self.stack.push(Value::Number(a - b)),
+                        _ => return Err("Operands must be numbers.".to_string()),
                     };
                 }
                 Op::Multiply => {
-                    match (&self.stack.pop(), &self.stack.pop()) {
-                        (Some(Value::Number(a)), Some(Value::Number(b))) => self.stack.push(Value::Number(b * a)),
-                        (a, b) => return InterpretResult::RuntimeError(
-                            format!("tried to perform binop '*' but arguments are invalid: {:?} {:?}", a, b)
-                        )
+                    match self.pop_pair()? {
+                        (Value::Number(a), Value::Number(b)) => self.stack.push(Value::Number(a * b)),
+                        _ => return Err("Operands must be numbers.".to_string()),
                     };
                 }
                 Op::Divide => {
-                    match (&self.stack.pop(), &self.stack.pop()) {
-                        (Some(Value::Number(a)), Some(Value::Number(b))) => self.stack.push(Value::Number(b / a)),
-                        (a, b) => return InterpretResult::RuntimeError(
-                            format!("tried to perform binop '/' but arguments are invalid: {:?} {:?}", a, b)
-                        )
+                    match self.pop_pair()? {
+                        (Value::Number(a), Value::Number(b)) => self.stack.push(Value::Number(a / b)),
+                        _ => return Err("Operands must be numbers.".to_string()),
                     };
                 }
 
                 Op::Greater => {
-                    match (&self.stack.pop(), &self.stack.pop()) {
-                        (Some(Value::Number(a)), Some(Value::Number(b))) => self.stack.push(Value::Bool(b > a)),
-                        (Some(Value::String(a)), Some(Value::String(b))) => self.stack.push(Value::Bool(b > a)),
-                        (a, b) => return InterpretResult::RuntimeError(
-                            format!("tried to perform binop '>' but arguments are invalid: {:?} {:?}", a, b)
-                        )
+                    match self.pop_pair()? {
+                        (Value::Number(a), Value::Number(b)) => self.stack.push(Value::Bool(a > b)),
+                        (Value::String(a), Value::String(b)) => self.stack.push(Value::Bool(a > b)),
+                        _ => return Err("Operands must be two numbers or two strings.".to_string()),
                     };
                 }
                 Op::Less => {
-                    match (&self.stack.pop(), &self.stack.pop()) {
-                        (Some(Value::Number(a)), Some(Value::Number(b))) => self.stack.push(Value::Bool(b < a)),
-                        (Some(Value::String(a)), Some(Value::String(b))) => self.stack.push(Value::Bool(b < a)),
-                        (a, b) => return InterpretResult::RuntimeError(
-                            format!("tried to perform binop '<' but arguments are invalid: {:?} {:?}", a, b)
-                        )
+                    match self.pop_pair()? {
+                        (Value::Number(a), Value::Number(b)) => self.stack.push(Value::Bool(a < b)),
+                        (Value::String(a), Value::String(b)) => self.stack.push(Value::Bool(a < b)),
+                        _ => return Err("Operands must be two numbers or two strings.".to_string()),
                     };
                 }
                 Op::Equal => {
-                    match (&self.stack.pop(), &self.stack.pop()) {
-                        (Some(Value::Bool(a)), Some(Value::Bool(b))) => self.stack.push(Value::Bool(b == a)),
-                        (Some(Value::Number(a)), Some(Value::Number(b))) => self.stack.push(Value::Bool(b == a)),
-                        (Some(Value::String(a)), Some(Value::String(b))) => self.stack.push(Value::Bool(b == a)),
-                        // (Some(_), Some(_)) => self.stack.push(Value::Bool(false)),
-                        (a, b) => return InterpretResult::RuntimeError(
-                            format!("tried to perform binop '==' but arguments are invalid: {:?} {:?}", a, b)
-                        )
-                    };
-
+                    let (a, b) = self.pop_pair()?;
+                    self.stack.push(Value::Bool(a == b));
                 }
 
-                Op::Print => match self.stack.last() {
-                    Some(_) => {
-                        println!("{}", self.stack.pop().unwrap());
-                    }
-                    None => {
-                        return InterpretResult::RuntimeError("nothing to dump".to_string());
-                    }
-                },
-            }
-            // println!("\n{:04} {:?}",self.ip, op);
-            // println!("\tstack: {:?}", self.stack);
-            // println!("\tsymtable: {:?}", self.symtable);
-            // if self.stack.len() > 3 {
-            //     panic!("stack overflow");
-            // }
-
-            self.ip += 1;
-            if self.ip >= self.chunk.code.len() {
-                return InterpretResult::InterpretOk;
+                Op::Print => {
+                    let value = self.pop().map_err(|_| "nothing to dump".to_string())?;
+                    println!("{}", self.heap.display(&value));
+                }
             }
         }
     }
@@ -0,0 +1,122 @@
+use rlox::LoxError;
+
+mod common;
+use common::{eval, display};
+
+#[test]
+fn classes_and_instances_display() {
+    assert_eq!(display("class Point {} Point;"), "Point");
+    assert_eq!(display("class Point {} Point();"), "Point instance");
+}
+
+#[test]
+fn fields() {
+    let source = r#"
+        class Pair {}
+        var pair = Pair();
+        pair.first = 1;
+        pair.second = 2;
+        pair.first + pair.second;
+    "#;
+    assert_eq!(display(source), "3");
+    assert_eq!(display("class A {} var a = A(); a.x = a.y = 3; a.x + a.y;"), "6");
+}
+
+#[test]
+fn methods_bind_this() {
+    let source = r#"
+        class Counter {
+            bump() {
+                this.count = this.count + 1;
+                return this;
+            }
+        }
+        var c = Counter();
+        c.count = 0;
+        var bump = c.bump;
+        bump();
+        c.bump().bump();
+        c.count;
+    "#;
+    assert_eq!(display(source), "3");
+}
+
+#[test]
+fn this_is_captured_by_closures() {
+    let source = r#"
+        class Greeter {
+            greeter() {
+                fun greet() { return "hi " + this.name; }
+                return greet;
+            }
+        }
+        var g = Greeter();
+        g.name = "lox";
+        g.greeter()();
+    "#;
+    assert_eq!(display(source), "hi lox");
+}
+
+#[test]
+fn initializer() {
+    let source = r#"
+        class Point {
+            init(x, y) {
+                this.x = x;
+                this.y = y;
+            }
+            sum() { return this.x + this.y; }
+        }
+        Point(3, 4).sum();
+    "#;
+    assert_eq!(display(source), "7");
+
+    let early_return = r#"
+        class A {
+            init() {
+                this.v = 1;
+                return;
+                this.v = 2;
+            }
+        }
+        var a = A();
+        a.init().v;
+    "#;
+    assert_eq!(display(early_return), "1");
+}
+
+#[test]
+fn fields_shadow_methods() {
+    let source = r#"
+        class A { m() { return "method"; } }
+        var a = A();
+        fun f() { return "field"; }
+        a.m = f;
+        a.m();
+    "#;
+    assert_eq!(display(source), "field");
+}
+
+#[test]
+fn local_class() {
+    assert_eq!(display("var r = nil; { class L { v() { return 2; } } r = L().v(); } r;"), "2");
+}
+
+#[test]
+fn runtime_errors() {
+    assert!(matches!(eval("class A {} A().missing;"), Err(LoxError::Runtime(_))));
+    assert!(matches!(eval("var a = 1; a.x;"), Err(LoxError::Runtime(_))));
+    assert!(matches!(eval("var a = 1; a.x = 2;"), Err(LoxError::Runtime(_))));
+    assert!(matches!(eval("class A {} A(1);"), Err(LoxError::Runtime(_))));
+    assert!(matches!(eval("class A { init(a) {} } A();"), Err(LoxError::Runtime(_))));
+}
+
+#[test]
+fn compile_errors() {
+    assert!(matches!(eval("print this;"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("fun f() { return this; }"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("class A { init() { return 1; } }"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("class { }"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("class A { 1 }"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("class A {} A().1;"), Err(LoxError::Compile(_))));
+}
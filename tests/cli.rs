@@ -0,0 +1,98 @@
+//! Runs the `rlox` binary the way a user would.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn rlox(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run rlox")
+}
+
+/// Writes `source` to a script file private to the calling test.
+fn script(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rlox-cli-{}-{}.lox", std::process::id(), name));
+    std::fs::write(&path, source).unwrap();
+    path
+}
+
+fn run_script(name: &str, source: &str) -> Output {
+    let path = script(name, source);
+    let output = rlox(&[path.to_str().unwrap()]);
+    std::fs::remove_file(&path).ok();
+    output
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn runs_a_file() {
+    let output = run_script("ok", "var a = 1;\nprint a + 2;\nprint \"done\";\n");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "3\ndone\n");
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn compile_errors_exit_with_65() {
+    let output = run_script("compile", "print 1;\nprint 1 +;\n");
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(stderr(&output), "[line 2] Error at ';': Expect expression.\n");
+}
+
+#[test]
+fn runtime_errors_exit_with_70() {
+    let output = run_script("runtime", "print \"before\";\nprint -nil;\nprint \"after\";\n");
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(stdout(&output), "before\n");
+    let stderr = stderr(&output);
+    let mut lines = stderr.lines();
+    assert_eq!(lines.next(), Some("Operand must be a number."));
+    assert_eq!(lines.next(), Some("[line 2] in script"));
+}
+
+#[test]
+fn missing_file_exits_with_74() {
+    let output = rlox(&["/nonexistent/rlox-cli-test.lox"]);
+    assert_eq!(output.status.code(), Some(74));
+    assert!(stderr(&output).starts_with("Could not read file"), "{}", stderr(&output));
+}
+
+#[test]
+fn too_many_arguments_exit_with_64() {
+    let output = rlox(&["a.lox", "b.lox"]);
+    assert_eq!(output.status.code(), Some(64));
+    assert!(stderr(&output).starts_with("Usage: rlox"), "{}", stderr(&output));
+}
+
+#[test]
+fn repl_keeps_state_and_exits_on_eof() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run rlox");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"var a = 20;\nprint a + 1;\nprint missing;\nprint a * 2;\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    // errors are reported, but don't end the session
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "> > 21\n> > 40\n> \n");
+    assert!(stderr(&output).starts_with("Undefined variable 'missing'."), "{}", stderr(&output));
+}
@@ -0,0 +1,142 @@
+use rlox::Value;
+
+mod common;
+use common::{eval, number};
+
+#[test]
+fn counter_outlives_its_frame() {
+    let source = r#"
+        fun make_counter() {
+            var count = 0;
+            fun counter() {
+                count = count + 1;
+                return count;
+            }
+            return counter;
+        }
+        var a = make_counter();
+        var b = make_counter();
+        a();
+        a();
+        b();
+        a() * 10 + b();
+    "#;
+    assert_eq!(number(source), 32.0);
+}
+
+#[test]
+fn adder() {
+    let source = r#"
+        fun make_adder(n) {
+            fun add(x) { return x + n; }
+            return add;
+        }
+        var add2 = make_adder(2);
+        var add10 = make_adder(10);
+        add2(1) + add10(1);
+    "#;
+    assert_eq!(number(source), 14.0);
+}
+
+#[test]
+fn nested_capture_through_intermediate_function() {
+    let source = r#"
+        fun outer() {
+            var x = 1;
+            fun middle() {
+                fun inner() { return x; }
+                return inner;
+            }
+            x = 2;
+            return middle;
+        }
+        outer()()();
+    "#;
+    assert_eq!(number(source), 2.0);
+}
+
+#[test]
+fn closures_share_captured_variable() {
+    let source = r#"
+        var get = nil;
+        var set = nil;
+        {
+            var shared = "before";
+            fun g() { return shared; }
+            fun s(v) { shared = v; }
+            get = g;
+            set = s;
+        }
+        set("after");
+        get();
+    "#;
+    assert!(matches!(eval(source), Ok(Value::String(s)) if &*s == "after"));
+}
+
+#[test]
+fn capture_sees_assignments_while_open() {
+    let source = r#"
+        var result = nil;
+        {
+            var a = 1;
+            fun f() { return a; }
+            a = 2;
+            result = f();
+        }
+        result;
+    "#;
+    assert_eq!(number(source), 2.0);
+}
+
+#[test]
+fn loop_body_locals_are_fresh_each_iteration() {
+    let source = r#"
+        var first = nil;
+        var second = nil;
+        {
+            var i = 0;
+            while (i < 2) {
+                var j = i;
+                fun f() { return j; }
+                if (i == 0) first = f; else second = f;
+                i = i + 1;
+            }
+        }
+        first() * 10 + second();
+    "#;
+    assert_eq!(number(source), 1.0);
+}
+
+#[test]
+fn loop_variable_is_shared_by_every_iteration() {
+    let source = r#"
+        var first = nil;
+        var second = nil;
+        {
+            var i = 0;
+            while (i < 2) {
+                fun f() { return i; }
+                if (i == 0) first = f; else second = f;
+                i = i + 1;
+            }
+        }
+        first() * 10 + second();
+    "#;
+    assert_eq!(number(source), 22.0);
+}
+
+#[test]
+fn local_function_recursion() {
+    let source = r#"
+        var result = nil;
+        {
+            fun count(n) {
+                if (n == 0) return 0;
+                return 1 + count(n - 1);
+            }
+            result = count(10);
+        }
+        result;
+    "#;
+    assert_eq!(number(source), 10.0);
+}
@@ -0,0 +1,102 @@
+use rlox::chunk::{Chunk, Doc, DocTarget};
+use rlox::parser::compile;
+use rlox::{LoxError, Value};
+
+mod common;
+use common::{eval, number};
+
+/// Every doc comment in the chunk and the functions nested in it.
+fn docs(chunk: &Chunk) -> Vec<Doc> {
+    let mut found = chunk.docs.clone();
+    for constant in &chunk.constants {
+        if let Value::Function(function) = constant {
+            found.extend(docs(&function.chunk));
+        }
+    }
+    found
+}
+
+fn doc(target: DocTarget, name: &str, text: &str, line: usize) -> Doc {
+    Doc {
+        target,
+        name: name.to_string(),
+        text: text.to_string(),
+        line,
+    }
+}
+
+#[test]
+fn block_comments_are_ignored() {
+    let source = r#"
+        var a = 1; /* a = 2;
+        /* nested */
+        a = 3; */
+        a /* inline */ + 1;
+    "#;
+    assert_eq!(number(source), 2.0);
+}
+
+#[test]
+fn unterminated_block_comment() {
+    match eval("var a = 1; /* /* */ a;") {
+        Err(LoxError::Compile(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].to_string(), "[line 1] Error: Unterminated block comment.");
+        }
+        other => panic!("expected a compile error, got {:?}", other),
+    }
+}
+
+#[test]
+fn doc_comments_attach_to_declarations() {
+    let source = r#"/// The answer.
+var answer = 42;
+
+/// Adds two numbers.
+/// Both must be numbers.
+fun add(a, b) {
+    /// Scratch space.
+    var sum = a + b;
+    return sum;
+}
+
+/// A point.
+class Point {
+    /// Makes a point.
+    init(x) { this.x = x; }
+
+    norm() { return this.x; }
+}
+
+// plain comments aren't docs
+fun plain() {}
+"#;
+    let chunk = compile(source).unwrap();
+    assert_eq!(
+        docs(&chunk),
+        vec![
+            doc(DocTarget::Variable, "answer", "The answer.", 2),
+            doc(DocTarget::Function, "add", "Adds two numbers.\nBoth must be numbers.", 6),
+            doc(DocTarget::Class, "Point", "A point.", 13),
+            doc(DocTarget::Method, "Point.init", "Makes a point.", 15),
+            doc(DocTarget::Variable, "sum", "Scratch space.", 8),
+        ]
+    );
+}
+
+#[test]
+fn doc_comments_elsewhere_are_dropped() {
+    let source = r#"
+        /// Not attached to anything.
+        print 1;
+        var a = 1 + /// nor this
+            2;
+        /// Stale.
+        {
+            var b = 3;
+        }
+    "#;
+    let chunk = compile(source).unwrap();
+    assert!(docs(&chunk).is_empty(), "{:?}", docs(&chunk));
+    assert_eq!(number("/// a\n1 + /// b\n 2;"), 3.0);
+}
@@ -0,0 +1,65 @@
+//! Helpers shared by the integration tests. Each test crate uses only some
+//! of them.
+#![allow(dead_code)]
+
+use rlox::{Interpreter, LoxError, Value};
+
+/// Runs `source` on a fresh interpreter.
+pub fn eval(source: &str) -> Result<Value, LoxError> {
+    Interpreter::new().eval(source)
+}
+
+pub fn number(source: &str) -> f64 {
+    match eval(source) {
+        Ok(Value::Number(n)) => n,
+        other => panic!("expected a number from {:?}, got {:?}", source, other),
+    }
+}
+
+pub fn string(source: &str) -> String {
+    match eval(source) {
+        Ok(Value::String(s)) => s.to_string(),
+        other => panic!("expected a string from {:?}, got {:?}", source, other),
+    }
+}
+
+/// The result of `source` as `print` would show it.
+pub fn display(source: &str) -> String {
+    let mut interpreter = Interpreter::new();
+    match interpreter.eval(source) {
+        Ok(value) => interpreter.display(&value).to_string(),
+        Err(e) => panic!("{:?} failed: {}", source, e),
+    }
+}
+
+/// The message of the runtime error `source` fails with.
+pub fn runtime_error(source: &str) -> String {
+    match eval(source) {
+        Err(LoxError::Runtime(e)) => e.message,
+        other => panic!("expected a runtime error from {:?}, got {:?}", source, other),
+    }
+}
+
+/// The first compile error `source` is rejected with.
+pub fn compile_error(source: &str) -> String {
+    match eval(source) {
+        Err(LoxError::Compile(errors)) => errors[0].to_string(),
+        other => panic!("expected a compile error from {:?}, got {:?}", source, other),
+    }
+}
+
+/// Small deterministic PRNG so failures are reproducible.
+pub struct XorShift(pub u64);
+
+impl XorShift {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
@@ -0,0 +1,95 @@
+use rlox::{Interpreter, LoxError, Value};
+
+mod common;
+use common::{compile_error, eval, number};
+
+#[test]
+fn call_with_arguments_and_return() {
+    assert_eq!(number("fun add(a, b) { return a + b; } add(1, 2);"), 3.0);
+    assert_eq!(number("fun three() { return 3; } three() * three();"), 9.0);
+}
+
+#[test]
+fn implicit_return_is_nil() {
+    assert!(matches!(eval("fun f() {} f();"), Ok(Value::Nil)));
+    assert!(matches!(eval("fun f() { return; } f();"), Ok(Value::Nil)));
+}
+
+#[test]
+fn recursion() {
+    let fib = "fun fib(n) { if (n < 2) return n; return fib(n - 2) + fib(n - 1); } fib(15);";
+    assert_eq!(number(fib), 610.0);
+}
+
+#[test]
+fn locals_are_relative_to_the_frame() {
+    let source = r#"
+        var result = 0;
+        {
+            var a = 100;
+            fun f(x, y) {
+                var z = x * y;
+                return z + 1;
+            }
+            result = a + f(2, 3);
+        }
+        result;
+    "#;
+    assert_eq!(number(source), 107.0);
+}
+
+#[test]
+fn functions_are_values() {
+    let source = "fun f() { return 1; } var g = f; g();";
+    assert_eq!(number(source), 1.0);
+    let mut interpreter = Interpreter::new();
+    let f = interpreter.eval("fun f() {} f;").unwrap();
+    assert_eq!(interpreter.display(&f).to_string(), "<fn f>");
+}
+
+#[test]
+fn runtime_errors() {
+    assert!(matches!(eval("fun f(a) {} f();"), Err(LoxError::Runtime(_))));
+    assert!(matches!(eval("fun f(a) {} f(1, 2);"), Err(LoxError::Runtime(_))));
+    assert!(matches!(eval("var a = 1; a();"), Err(LoxError::Runtime(_))));
+    assert!(matches!(eval("fun f() { return f(); } f();"), Err(LoxError::Runtime(_))));
+}
+
+#[test]
+fn compile_errors() {
+    assert!(matches!(eval("return 1;"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("fun (a) {}"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("fun f(a b) {}"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("fun f(1) {}"), Err(LoxError::Compile(_))));
+
+    let params: Vec<String> = (0..256).map(|i| format!("p{}", i)).collect();
+    let source = format!("fun f({}) {{}}", params.join(", "));
+    assert!(matches!(eval(&source), Err(LoxError::Compile(_))));
+}
+
+#[test]
+fn local_declarations() {
+    assert!(matches!(eval("fun f() { var a; return a; } f();"), Ok(Value::Nil)));
+    assert_eq!(number("fun f() { var a; a = 3; return a; } f();"), 3.0);
+
+    assert_eq!(
+        compile_error("fun f() {\n  var a = 1;\n  var a = 2;\n}"),
+        "[line 3] Error at 'a': Already a variable with this name in this scope."
+    );
+    assert_eq!(
+        compile_error("var a = 1;\n{\n  var a = a;\n}"),
+        "[line 3] Error at 'a': Can't read local variable in its own initializer."
+    );
+}
+
+#[test]
+fn names_starting_with_keywords() {
+    let source = r#"
+        fun format(android, variable) { return android + variable; }
+        var order = 1;
+        var classy = 2;
+        var thistle = format(order, classy);
+        thistle;
+    "#;
+    assert_eq!(number(source), 3.0);
+}
@@ -0,0 +1,219 @@
+//! Fuzz-style checks that the Scanner -> Parser -> VM pipeline is total:
+//! malformed programs must come back as a `LoxError`, never as a panic.
+
+use std::panic;
+
+use rlox::{Interpreter, LoxError, Value};
+
+mod common;
+use common::XorShift;
+
+/// Programs that must be rejected with a diagnostic.
+const MALFORMED: &[&str] = &[
+    // scanner
+    "print 12abc;",
+    "print \"unterminated;",
+    "\"",
+    "print @;",
+    "var é = 1;",
+    "#",
+    "print \"\\q\";",
+    "print \"\\u{110000}\";",
+    "print \"${\";",
+    "print \"${1\";",
+    "print \"${}\";",
+    "print \"a ${ \"b ${ } \";",
+    "print 1.2.3;",
+    "print 0x;",
+    "print 1e+;",
+    // expressions in the wrong place
+    ",",
+    ".",
+    "class",
+    "}",
+    "{",
+    "print ,;",
+    "print 1,2;",
+    "print a.b;",
+    "print fun;",
+    "print (;",
+    "print ();",
+    "print );",
+    "1 +;",
+    "* 2;",
+    "!;",
+    "-;",
+    "print 1 2;",
+    "print 1 == ;",
+    "a = ;",
+    "1 = 2;",
+    "a + b = 3;",
+    "(a) = 3;",
+    "print 1 or;",
+    "print and 1;",
+    // statements
+    "var;",
+    "var 1 = 2;",
+    "var a 1;",
+    "var a = 1",
+    "print",
+    "print 1",
+    "if",
+    "if (",
+    "if (true",
+    "if (true)",
+    "if (true) print 1; else",
+    "while",
+    "while (",
+    "while (true",
+    "{ var a = 1; var a = 2; }",
+    "{ print 1;",
+    "} print 1;",
+    "return 1;",
+    // runtime errors
+    "print -true;",
+    "print 1 + \"a\";",
+    "print \"a\" - \"b\";",
+    "print 1 < true;",
+    "print undefined;",
+    "undefined = 1;",
+    "{ var a = 1; a = b; }",
+];
+
+/// Valid, loop-free programs used as seeds for mutation.
+const SEEDS: &[&str] = &[
+    "print 1 + 2 * 3;",
+    "var a = \"lm\" + \"ao\"; print a;",
+    "{ var a = 1; { var b = a * 3; print b; } print a; }",
+    "var a = 1; if (a < 2 and a > 0) { print \"yes\"; } else { print \"no\"; }",
+    "var a = true; var b = !a or a; print b == false;",
+    "{ var x = 10; x = x - 1; print (x / 3) >= 3; }",
+    "var n = 0x1f; print \"n = ${n * 1.5e1} \\u{e9}\\t${\"${n}\"}\";",
+];
+
+/// Lexemes the token-soup generator picks from. Loops are left out on
+/// purpose: a randomly well-formed `while` could legitimately never end.
+const ALPHABET: &[&str] = &[
+    "(", ")", "{", "}", ",", ".", "-", "+", ";", "/", "*", "!", "!=", "=", "==", ">", ">=",
+    "<", "<=", "a", "b", "c", "\"s\"", "\"", "1", "12abc", "0", "and", "class", "else",
+    "false", "fun", "if", "nil", "or", "print", "return", "super", "this", "true", "var",
+    "@", "#", "\u{e9}", "//", "\n", "\"${", "}\"", "\\", "1.5e3", "0xff",
+];
+
+fn eval_without_panic(source: &str) -> Result<Value, LoxError> {
+    let owned = source.to_string();
+    // collect as often as possible so rooting bugs show up as panics
+    let run = move || {
+        let mut interpreter = Interpreter::new();
+        interpreter.vm().heap_mut().set_stress(true);
+        interpreter.eval(&owned)
+    };
+    match panic::catch_unwind(run) {
+        Ok(result) => result,
+        Err(_) => panic!("interpreter panicked on {:?}", source),
+    }
+}
+
+#[test]
+fn malformed_programs_report_errors() {
+    for source in MALFORMED {
+        assert!(
+            eval_without_panic(source).is_err(),
+            "expected an error for {:?}",
+            source
+        );
+    }
+}
+
+/// `open`, `n` times, around `inner`, then `close` `n` times.
+fn nested(open: &str, inner: &str, close: &str, n: usize) -> String {
+    format!("{}{}{}", open.repeat(n), inner, close.repeat(n))
+}
+
+#[test]
+fn deep_nesting_is_a_compile_error() {
+    let sources = [
+        nested("print ", &nested("(", "1", ")", 100_000), ";", 1),
+        nested("print ", &nested("-", "1", "", 200_000), ";", 1),
+        nested("{", "print 1;", "}", 100_000),
+        nested("print ", &nested("\"${", "1", "}\"", 50_000), ";", 1),
+        nested("if (true) ", "print 1;", "", 100_000),
+        nested("fun f() {", "", "}", 100_000),
+    ];
+    for source in &sources {
+        match eval_without_panic(source) {
+            Err(LoxError::Compile(errors)) => {
+                assert_eq!(errors.len(), 1, "{:?}", errors);
+                assert_eq!(errors[0].message, "Too much nesting.");
+            }
+            other => panic!("expected a compile error, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn reasonable_nesting_is_allowed() {
+    let sources = [
+        nested("print ", &nested("(", "1", ")", 100), ";", 1),
+        nested("print ", &nested("-", "1", "", 100), ";", 1),
+        nested("{", "print 1;", "}", 100),
+        nested("print ", &nested("\"${", "1", "}\"", 50), ";", 1),
+    ];
+    for source in &sources {
+        assert!(eval_without_panic(source).is_ok(), "{:.40}...", source);
+    }
+}
+
+#[test]
+fn seeds_are_valid() {
+    for source in SEEDS {
+        assert!(eval_without_panic(source).is_ok(), "seed failed: {:?}", source);
+    }
+}
+
+#[test]
+fn random_token_soup_never_panics() {
+    let mut rng = XorShift(0x5eed_1234_abcd_0001);
+    for _ in 0..2000 {
+        let len = rng.below(40);
+        let mut source = String::new();
+        for _ in 0..len {
+            source.push_str(ALPHABET[rng.below(ALPHABET.len())]);
+            if rng.below(4) != 0 {
+                source.push(' ');
+            }
+        }
+        let _ = eval_without_panic(&source);
+    }
+}
+
+#[test]
+fn mutated_seeds_never_panic() {
+    let mut rng = XorShift(0x0bad_c0de_dead_beef);
+    let charset: Vec<char> = "(){},.-+;/*!=<>\"ab1 \n@".chars().collect();
+
+    for _ in 0..2000 {
+        let mut chars: Vec<char> = SEEDS[rng.below(SEEDS.len())].chars().collect();
+        for _ in 0..=rng.below(4) {
+            if chars.is_empty() {
+                break;
+            }
+            let at = rng.below(chars.len());
+            match rng.below(4) {
+                0 => {
+                    chars.remove(at);
+                }
+                1 => chars.insert(at, charset[rng.below(charset.len())]),
+                2 => chars.truncate(at),
+                _ => {
+                    let end = (at + rng.below(8)).min(chars.len());
+                    let slice: Vec<char> = chars[at..end].to_vec();
+                    let to = rng.below(chars.len());
+                    chars.splice(to..to, slice);
+                }
+            }
+        }
+        let source: String = chars.into_iter().collect();
+        let _ = eval_without_panic(&source);
+    }
+}
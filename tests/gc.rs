@@ -0,0 +1,156 @@
+use rlox::{Interpreter, LoxError, Value};
+
+/// Programs that allocate every kind of heap object and keep some of them
+/// alive only through upvalues, fields, methods or the stack.
+const PROGRAMS: &[&str] = &[
+    r#"
+        fun counter() {
+            var count = 0;
+            fun increment() { count = count + 1; return count; }
+            return increment;
+        }
+        var c = counter();
+        c(); c();
+        c();
+    "#,
+    r#"
+        fun outer() {
+            var a = 1;
+            var b = 2;
+            fun middle() {
+                fun inner() { return a + b; }
+                return inner;
+            }
+            return middle;
+        }
+        outer()()();
+    "#,
+    r#"
+        class Node {
+            init(value, next) { this.value = value; this.next = next; }
+            sum(length) {
+                if (length == 1) return this.value;
+                return this.value + this.next.sum(length - 1);
+            }
+        }
+        var list = Node(0, nil);
+        for (var i = 1; i <= 10; i = i + 1) list = Node(i, list);
+        list.sum(11);
+    "#,
+    r#"
+        class A { name() { return "A"; } }
+        class B < A { name() { return "B" + super.name(); } }
+        var method = B().name;
+        method();
+    "#,
+    r#"
+        var total = 0;
+        for (var i = 0; i < 50; i = i + 1) {
+            class Temp { init(n) { this.n = n; } get() { return this.n; } }
+            var t = Temp(i);
+            var f = t.get;
+            total = total + f();
+        }
+        total;
+    "#,
+];
+
+fn eval(interpreter: &mut Interpreter, source: &str) -> Result<String, LoxError> {
+    let value = interpreter.eval(source)?;
+    Ok(interpreter.display(&value).to_string())
+}
+
+#[test]
+fn stress_mode_matches_normal_mode() {
+    for source in PROGRAMS {
+        let expected = eval(&mut Interpreter::new(), source);
+        assert!(expected.is_ok(), "{}: {:?}", source, expected);
+
+        let mut stressed = Interpreter::new();
+        stressed.vm().heap_mut().set_stress(true);
+        let result = eval(&mut stressed, source);
+        assert_eq!(format!("{:?}", result), format!("{:?}", expected), "{}", source);
+        assert!(stressed.vm().heap().collections() > 0);
+    }
+}
+
+#[test]
+fn cycles_are_collected() {
+    let mut interpreter = Interpreter::new();
+    interpreter.vm().heap_mut().set_threshold(4096);
+    let source = r#"
+        class Pair {}
+        var kept = Pair();
+        for (var i = 0; i < 1000; i = i + 1) {
+            var a = Pair();
+            var b = Pair();
+            a.other = b;
+            b.other = a;
+        }
+    "#;
+    interpreter.eval(source).unwrap();
+    assert!(interpreter.vm().heap().collections() > 0);
+
+    interpreter.vm().collect_garbage();
+    // the class and the instance in `kept`
+    assert_eq!(interpreter.vm().heap().len(), 2);
+    assert!(matches!(interpreter.get_global("kept"), Some(Value::Instance(_))));
+}
+
+#[test]
+fn threshold_delays_collection() {
+    let mut interpreter = Interpreter::new();
+    interpreter.vm().heap_mut().set_threshold(usize::MAX);
+    interpreter.eval("class C {} for (var i = 0; i < 100; i = i + 1) C();").unwrap();
+    assert_eq!(interpreter.vm().heap().collections(), 0);
+    assert!(interpreter.vm().heap().len() > 100);
+
+    interpreter.vm().collect_garbage();
+    assert_eq!(interpreter.vm().heap().len(), 1);
+}
+
+#[test]
+fn globals_survive_between_evals() {
+    let mut interpreter = Interpreter::new();
+    interpreter.vm().heap_mut().set_stress(true);
+    interpreter.eval("fun make(n) { fun get() { return n; } return get; } var g = make(7);").unwrap();
+    interpreter.eval("class Junk {} for (var i = 0; i < 10; i = i + 1) Junk();").unwrap();
+    assert!(matches!(interpreter.eval("g();"), Ok(Value::Number(n)) if n == 7.0));
+}
+
+#[test]
+fn results_held_across_a_collection_go_stale() {
+    let mut interpreter = Interpreter::new();
+    interpreter.vm().heap_mut().set_stress(true);
+    let instance = interpreter.eval("class A {} A();").unwrap();
+    assert_eq!(interpreter.display(&instance).to_string(), "A instance");
+
+    // reuses the instance's slot for another instance of the same class
+    let kept = interpreter.eval("var b = A(); b;").unwrap();
+    let Value::Instance(handle) = instance else {
+        panic!("expected an instance, got {:?}", instance);
+    };
+    assert!(interpreter.vm().heap().get(handle).is_none());
+    assert_eq!(interpreter.display(&instance).to_string(), "<collected>");
+    assert!(matches!(kept, Value::Instance(kept) if kept != handle));
+    assert_eq!(interpreter.display(&kept).to_string(), "A instance");
+}
+
+#[test]
+fn stale_values_cant_be_passed_back_in() {
+    let mut interpreter = Interpreter::new();
+    interpreter.vm().heap_mut().set_stress(true);
+    let stale = interpreter.eval("class A { m() { return 1; } } A();").unwrap();
+    interpreter.eval("A();").unwrap();
+    assert_eq!(interpreter.display(&stale).to_string(), "<collected>");
+
+    assert!(!interpreter.set_global("stale", stale));
+    assert!(interpreter.get_global("stale").is_none());
+    assert!(matches!(interpreter.eval("stale.m();"), Err(LoxError::Runtime(_))));
+    assert!(matches!(interpreter.eval("stale.x = 1;"), Err(LoxError::Runtime(_))));
+
+    // live objects still can be
+    let live = interpreter.eval("A();").unwrap();
+    assert!(interpreter.set_global("live", live));
+    assert!(matches!(interpreter.eval("live.m();"), Ok(Value::Number(n)) if n == 1.0));
+}
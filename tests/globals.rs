@@ -0,0 +1,95 @@
+use std::rc::Rc;
+
+use rlox::{Interpreter, Value};
+
+mod common;
+use common::{eval, number, runtime_error};
+
+#[test]
+fn globals_are_late_bound() {
+    let source = r#"
+        fun get() { return later; }
+        var later = 42;
+        get();
+    "#;
+    assert_eq!(number(source), 42.0);
+}
+
+#[test]
+fn undefined_globals() {
+    assert_eq!(runtime_error("print missing;"), "Undefined variable 'missing'.");
+    assert_eq!(runtime_error("missing = 1;"), "Undefined variable 'missing'.");
+    assert_eq!(
+        runtime_error("fun f() { return later; } f(); var later = 1;"),
+        "Undefined variable 'later'."
+    );
+}
+
+#[test]
+fn failed_assignment_does_not_define() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval("missing = 1;").is_err());
+    assert!(interpreter.eval("missing;").is_err());
+    assert!(interpreter.get_global("missing").is_none());
+}
+
+#[test]
+fn redefining_a_global() {
+    assert_eq!(number("var a = 1; var a = a + 1; a;"), 2.0);
+}
+
+#[test]
+fn declaring_without_an_initializer() {
+    assert!(matches!(eval("var a; a;"), Ok(Value::Nil)));
+    assert!(matches!(eval("var a = 1; var a; a;"), Ok(Value::Nil)));
+    assert_eq!(number("var a; a = 2; a;"), 2.0);
+}
+
+#[test]
+fn slots_persist_between_evals() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("fun get() { return answer; }").unwrap();
+    assert!(interpreter.eval("get();").is_err());
+
+    assert!(interpreter.set_global("answer", Value::Number(42.0)));
+    assert!(matches!(interpreter.eval("get();"), Ok(Value::Number(n)) if n == 42.0));
+}
+
+#[test]
+fn many_globals() {
+    let mut source = String::new();
+    for i in 0..300 {
+        source.push_str(&format!("var g{} = {};\n", i, i));
+    }
+    source.push_str("g0 + g150 + g299;");
+    assert_eq!(number(&source), 449.0);
+}
+
+#[test]
+fn set_global_reports_running_out_of_slots() {
+    let mut interpreter = Interpreter::new();
+    let mut defined = 0;
+    while interpreter.set_global(&format!("g{}", defined), Value::Number(defined as f64)) {
+        defined += 1;
+    }
+    // the built-ins took some slots
+    assert!(defined > 65000 && defined < 65536, "{}", defined);
+    let overflow = format!("g{}", defined);
+    assert!(interpreter.get_global(&overflow).is_none());
+    assert!(!interpreter.define_native("extra", 0, |_, _| Ok(Value::Nil)));
+
+    // existing globals can still be redefined
+    assert!(interpreter.set_global("g0", Value::Nil));
+    assert!(matches!(interpreter.get_global("g0"), Some(Value::Nil)));
+}
+
+#[test]
+fn string_constants_are_interned() {
+    let mut interpreter = Interpreter::new();
+    let (Ok(Value::String(a)), Ok(Value::String(b))) =
+        (interpreter.eval("\"shared\";"), interpreter.eval("var s = \"shared\"; s;"))
+    else {
+        panic!("expected strings");
+    };
+    assert!(Rc::ptr_eq(&a, &b));
+}
@@ -0,0 +1,113 @@
+use rlox::LoxError;
+
+mod common;
+use common::{eval, display};
+
+#[test]
+fn inherited_methods() {
+    let source = r#"
+        class A { name() { return "A"; } shout() { return this.name() + "!"; } }
+        class B < A {}
+        B().shout();
+    "#;
+    assert_eq!(display(source), "A!");
+}
+
+#[test]
+fn overrides_dispatch_dynamically() {
+    let source = r#"
+        class A { name() { return "A"; } shout() { return this.name() + "!"; } }
+        class B < A { name() { return "B"; } }
+        B().shout();
+    "#;
+    assert_eq!(display(source), "B!");
+}
+
+#[test]
+fn inherited_initializer() {
+    let source = r#"
+        class A { init(v) { this.v = v; } }
+        class B < A {}
+        B(5).v;
+    "#;
+    assert_eq!(display(source), "5");
+}
+
+#[test]
+fn super_calls_resolve_statically() {
+    let source = r#"
+        class A { method() { return "A"; } }
+        class B < A {
+            method() { return "B"; }
+            test() { return super.method(); }
+        }
+        class C < B {}
+        C().test();
+    "#;
+    assert_eq!(display(source), "A");
+}
+
+#[test]
+fn super_in_initializer_and_bound_super_method() {
+    let source = r#"
+        class Base {
+            init(a) { this.a = a; }
+            get() { return this.a; }
+        }
+        class Derived < Base {
+            init(a, b) {
+                super.init(a);
+                this.b = b;
+            }
+            getter() { return super.get; }
+        }
+        var d = Derived(1, 2);
+        d.getter()() + d.b;
+    "#;
+    assert_eq!(display(source), "3");
+}
+
+#[test]
+fn super_in_closure() {
+    let source = r#"
+        class A { say() { return "A"; } }
+        class B < A {
+            say() {
+                fun inner() { return super.say(); }
+                return inner;
+            }
+        }
+        B().say()();
+    "#;
+    assert_eq!(display(source), "A");
+}
+
+#[test]
+fn local_subclass() {
+    let source = r#"
+        var r = nil;
+        {
+            class A { v() { return 1; } }
+            class B < A { v() { return super.v() + 1; } }
+            r = B().v();
+        }
+        r;
+    "#;
+    assert_eq!(display(source), "2");
+}
+
+#[test]
+fn superclass_must_be_a_class() {
+    assert!(matches!(eval("var A = 1; class B < A {}"), Err(LoxError::Runtime(_))));
+    assert!(matches!(eval("class B < Missing {}"), Err(LoxError::Runtime(_))));
+}
+
+#[test]
+fn compile_errors() {
+    assert!(matches!(eval("class A < A {}"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("super.m();"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("fun f() { super.m(); }"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("class A { m() { super.m(); } }"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("class A {} class B < A { m() { super; } }"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("class B < {}"), Err(LoxError::Compile(_))));
+}
@@ -0,0 +1,220 @@
+use rlox::LoxError;
+
+mod common;
+use common::{eval, number};
+
+#[test]
+fn while_loop() {
+    let source = r#"
+        var i = 0;
+        var sum = 0;
+        while (i < 5) {
+            sum = sum + i;
+            i = i + 1;
+        }
+        sum;
+    "#;
+    assert_eq!(number(source), 10.0);
+}
+
+#[test]
+fn for_loop() {
+    let source = r#"
+        var sum = 0;
+        for (var i = 0; i < 5; i = i + 1) {
+            sum = sum + i;
+        }
+        sum;
+    "#;
+    assert_eq!(number(source), 10.0);
+}
+
+#[test]
+fn for_loop_with_expression_initializer() {
+    let source = r#"
+        var i = 0;
+        var sum = 0;
+        for (i = 1; i < 4; i = i + 1) sum = sum + i;
+        sum * 10 + i;
+    "#;
+    assert_eq!(number(source), 64.0);
+}
+
+#[test]
+fn for_loop_clauses_are_optional() {
+    let source = r#"
+        var i = 0;
+        for (; i < 3;) i = i + 1;
+        i;
+    "#;
+    assert_eq!(number(source), 3.0);
+
+    let source = r#"
+        fun first_square_over(n) {
+            for (var i = 0;; i = i + 1) {
+                if (i * i > n) return i;
+            }
+        }
+        first_square_over(50);
+    "#;
+    assert_eq!(number(source), 8.0);
+
+    let source = r#"
+        fun spin() {
+            var i = 0;
+            for (;;) {
+                i = i + 1;
+                if (i == 7) return i;
+            }
+        }
+        spin();
+    "#;
+    assert_eq!(number(source), 7.0);
+}
+
+#[test]
+fn for_loop_initializer_is_scoped() {
+    assert!(matches!(
+        eval("for (var i = 0; i < 1; i = i + 1) {} i;"),
+        Err(LoxError::Runtime(_))
+    ));
+    let source = r#"
+        var i = 100;
+        for (var i = 0; i < 3; i = i + 1) {}
+        i;
+    "#;
+    assert_eq!(number(source), 100.0);
+}
+
+#[test]
+fn nested_for_loops() {
+    let source = r#"
+        var count = 0;
+        for (var i = 0; i < 4; i = i + 1) {
+            for (var j = 0; j < i; j = j + 1) {
+                count = count + 1;
+            }
+        }
+        count;
+    "#;
+    assert_eq!(number(source), 6.0);
+}
+
+#[test]
+fn closures_capture_the_loop_variable() {
+    let source = r#"
+        var f = nil;
+        for (var i = 0; i < 3; i = i + 1) {
+            fun get() { return i; }
+            f = get;
+        }
+        f();
+    "#;
+    assert_eq!(number(source), 3.0);
+}
+
+#[test]
+fn for_loop_compile_errors() {
+    assert!(matches!(eval("for var i = 0; i < 1; i = i + 1) {}"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("for (var i = 0; i < 1 i = i + 1) {}"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("for (var i = 0; i < 1; i = i + 1 {}"), Err(LoxError::Compile(_))));
+}
+
+#[test]
+fn break_exits_the_innermost_loop() {
+    let source = r#"
+        var i = 0;
+        while (true) {
+            if (i == 4) break;
+            i = i + 1;
+        }
+        i;
+    "#;
+    assert_eq!(number(source), 4.0);
+
+    let source = r#"
+        var count = 0;
+        for (var i = 0; i < 3; i = i + 1) {
+            for (var j = 0; j < 10; j = j + 1) {
+                if (j == 2) break;
+                count = count + 1;
+            }
+        }
+        count;
+    "#;
+    assert_eq!(number(source), 6.0);
+}
+
+#[test]
+fn continue_skips_to_the_next_iteration() {
+    let source = r#"
+        var sum = 0;
+        for (var i = 0; i < 6; i = i + 1) {
+            if (i == 2 or i == 4) continue;
+            sum = sum + i;
+        }
+        sum;
+    "#;
+    assert_eq!(number(source), 9.0);
+
+    let source = r#"
+        var i = 0;
+        var sum = 0;
+        while (i < 6) {
+            i = i + 1;
+            if (i == 3) continue;
+            sum = sum + i;
+        }
+        sum;
+    "#;
+    assert_eq!(number(source), 18.0);
+}
+
+#[test]
+fn break_and_continue_discard_body_locals() {
+    let source = r#"
+        fun f() {
+            var total = 0;
+            for (var i = 0; i < 5; i = i + 1) {
+                var a = i;
+                {
+                    var b = a * 2;
+                    if (i == 1) continue;
+                    if (i == 3) break;
+                    total = total + b;
+                }
+            }
+            var after = 100;
+            return total + after;
+        }
+        f();
+    "#;
+    assert_eq!(number(source), 104.0);
+}
+
+#[test]
+fn break_closes_captured_locals() {
+    let source = r#"
+        var get = nil;
+        for (var i = 0; i < 5; i = i + 1) {
+            var captured = i * 10;
+            fun g() { return captured; }
+            get = g;
+            if (i == 2) break;
+        }
+        get();
+    "#;
+    assert_eq!(number(source), 20.0);
+}
+
+#[test]
+fn break_and_continue_outside_a_loop() {
+    assert!(matches!(eval("break;"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("continue;"), Err(LoxError::Compile(_))));
+    assert!(matches!(eval("if (true) { break; }"), Err(LoxError::Compile(_))));
+    // a function body doesn't inherit the enclosing loop
+    assert!(matches!(
+        eval("while (true) { fun f() { break; } }"),
+        Err(LoxError::Compile(_))
+    ));
+}
@@ -0,0 +1,108 @@
+use rlox::{Interpreter, LoxError, Value};
+
+mod common;
+use common::{eval, number, runtime_error};
+
+#[test]
+fn functions() {
+    let cases = [
+        ("sqrt(16)", 4.0),
+        ("pow(2, 10)", 1024.0),
+        ("floor(-1.5)", -2.0),
+        ("ceil(1.2)", 2.0),
+        ("round(2.5)", 3.0),
+        ("round(-2.5)", -3.0),
+        ("abs(-3)", 3.0),
+        ("min(3, -1)", -1.0),
+        ("max(3, -1)", 3.0),
+        ("sin(0)", 0.0),
+        ("cos(0)", 1.0),
+        ("tan(0)", 0.0),
+        ("log(1)", 0.0),
+        ("exp(0)", 1.0),
+        ("log(exp(2))", 2.0),
+    ];
+    for (expression, expected) in cases {
+        assert_eq!(number(&format!("{};", expression)), expected, "{}", expression);
+    }
+    assert!(number("sqrt(-1);").is_nan());
+}
+
+#[test]
+fn constants() {
+    assert_eq!(number("PI;"), std::f64::consts::PI);
+    assert_eq!(number("E;"), std::f64::consts::E);
+    assert!((number("sin(PI / 2);") - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn type_errors() {
+    assert_eq!(runtime_error("sqrt(\"4\");"), "Argument must be a number.");
+    assert_eq!(runtime_error("floor(nil);"), "Argument must be a number.");
+    assert_eq!(runtime_error("pow(2, true);"), "Arguments must be numbers.");
+    assert_eq!(runtime_error("max(\"a\", 1);"), "Arguments must be numbers.");
+    assert_eq!(runtime_error("random_int(1, \"6\");"), "Arguments must be numbers.");
+    assert_eq!(runtime_error("seed_random(nil);"), "Argument must be a number.");
+    assert_eq!(runtime_error("abs();"), "Expected 1 arguments but got 0.");
+}
+
+#[test]
+fn type_errors_report_the_call_site() {
+    match eval("var x = 1;\nprint sqrt(\"x\");") {
+        Err(LoxError::Runtime(e)) => {
+            assert_eq!(e.line(), 2);
+            assert_eq!(e.to_string().lines().next(), Some("Argument must be a number."));
+        }
+        other => panic!("expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn random_is_deterministic() {
+    let source = r#"
+        var s = "";
+        for (var i = 0; i < 5; i = i + 1) s = s + str(random()) + ",";
+        s;
+    "#;
+    let run = |prefix: &str| {
+        let mut interpreter = Interpreter::new();
+        let value = interpreter.eval(&format!("{}{}", prefix, source)).unwrap();
+        interpreter.display(&value).to_string()
+    };
+    // the same without a seed, and the same for the same seed
+    assert_eq!(run(""), run(""));
+    assert_eq!(run("seed_random(42);"), run("seed_random(42);"));
+    assert_ne!(run("seed_random(42);"), run("seed_random(43);"));
+
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("seed_random(1); var a = random(); seed_random(1);").unwrap();
+    assert!(matches!(interpreter.eval("a == random();"), Ok(Value::Bool(true))));
+}
+
+#[test]
+fn random_ranges() {
+    let mut interpreter = Interpreter::new();
+    let source = r#"
+        var ok = true;
+        var seen_min = false;
+        var seen_max = false;
+        for (var i = 0; i < 1000; i = i + 1) {
+            var r = random();
+            if (r < 0 or r >= 1) ok = false;
+            var n = random_int(-2, 3);
+            if (n < -2 or n > 3 or floor(n) != n) ok = false;
+            if (n == -2) seen_min = true;
+            if (n == 3) seen_max = true;
+        }
+        ok and seen_min and seen_max;
+    "#;
+    assert!(matches!(interpreter.eval(source), Ok(Value::Bool(true))));
+    assert_eq!(number("random_int(5, 5);"), 5.0);
+}
+
+#[test]
+fn random_int_arguments() {
+    assert_eq!(runtime_error("random_int(1.5, 3);"), "Arguments must be integers.");
+    assert_eq!(runtime_error("random_int(1, 0/0);"), "Arguments must be integers.");
+    assert_eq!(runtime_error("random_int(3, 1);"), "Minimum must not be greater than maximum.");
+}
@@ -0,0 +1,96 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use rlox::{Interpreter, LoxError, Value};
+
+mod common;
+use common::{display, runtime_error};
+
+#[test]
+fn clock() {
+    assert_eq!(display("clock;"), "<native fn>");
+    assert_eq!(display("var t = clock(); t >= 0 and clock() >= t;"), "true");
+}
+
+#[test]
+fn str() {
+    assert_eq!(display("str(1.5) + str(true) + str(nil);"), "1.5truenil");
+    assert_eq!(display("str(\"s\");"), "s");
+    assert_eq!(display("class A {} str(A) + \"/\" + str(A());"), "A/A instance");
+    assert_eq!(display("fun f() {} str(f) + str(str);"), "<fn f><native fn>");
+}
+
+#[test]
+fn num() {
+    assert_eq!(display("num(\"42\") + 1;"), "43");
+    assert_eq!(display("num(\" -2.5 \");"), "-2.5");
+    assert_eq!(display("num(7);"), "7");
+    assert_eq!(display("num(\"seven\");"), "nil");
+    assert_eq!(runtime_error("num(true);"), "Argument must be a number or a string.");
+}
+
+#[test]
+fn len() {
+    assert_eq!(display("len(\"\");"), "0");
+    assert_eq!(display("len(\"caf\\u{e9}\");"), "4");
+    assert_eq!(runtime_error("len(nil);"), "Argument must be a string.");
+}
+
+#[test]
+fn type_names() {
+    let cases = [
+        ("1", "number"),
+        ("true", "bool"),
+        ("\"s\"", "string"),
+        ("nil", "nil"),
+        ("clock", "function"),
+        ("f", "function"),
+        ("A", "class"),
+        ("A()", "instance"),
+        ("A().m", "function"),
+    ];
+    for (expression, name) in cases {
+        let source = format!("fun f() {{}} class A {{ m() {{}} }} type({});", expression);
+        assert_eq!(display(&source), name, "{}", expression);
+    }
+}
+
+#[test]
+fn arity_is_checked() {
+    assert_eq!(runtime_error("clock(1);"), "Expected 0 arguments but got 1.");
+    assert_eq!(runtime_error("len();"), "Expected 1 arguments but got 0.");
+}
+
+#[test]
+fn natives_are_values() {
+    assert_eq!(display("var f = len; f(\"abc\");"), "3");
+    assert_eq!(display("len == len;"), "true");
+    assert_eq!(display("len == str;"), "false");
+    assert_eq!(display("class A { init() { this.f = len; } } A().f(\"ab\");"), "2");
+}
+
+#[test]
+fn user_defined_natives() {
+    let mut interpreter = Interpreter::new();
+    let calls = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&calls);
+    interpreter.define_native("add", 2, move |_, args| {
+        counter.set(counter.get() + 1);
+        match args {
+            [Value::Number(a), Value::Number(b)] => Ok(Value::Number(a + b)),
+            _ => Err("add() takes two numbers.".to_string()),
+        }
+    });
+
+    assert!(matches!(interpreter.eval("add(1, add(2, 3));"), Ok(Value::Number(n)) if n == 6.0));
+    assert_eq!(calls.get(), 2);
+
+    match interpreter.eval("fun f() {\n  return add(1, nil);\n}\nf();") {
+        Err(LoxError::Runtime(e)) => {
+            assert_eq!(e.message, "add() takes two numbers.");
+            assert_eq!(e.line(), 2);
+            assert_eq!(e.trace.len(), 2);
+        }
+        other => panic!("expected a runtime error, got {:?}", other),
+    }
+}
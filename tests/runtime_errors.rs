@@ -0,0 +1,65 @@
+use rlox::{Interpreter, LoxError, RuntimeError};
+
+fn runtime_error(source: &str) -> RuntimeError {
+    match Interpreter::new().eval(source) {
+        Err(LoxError::Runtime(e)) => e,
+        other => panic!("expected a runtime error from {:?}, got {:?}", source, other),
+    }
+}
+
+#[test]
+fn messages() {
+    let cases = [
+        ("1 + true;", "Operands must be two numbers or two strings."),
+        ("\"a\" + 1;", "Operands must be two numbers or two strings."),
+        ("1 - \"a\";", "Operands must be numbers."),
+        ("nil * 2;", "Operands must be numbers."),
+        ("2 / false;", "Operands must be numbers."),
+        ("1 < \"a\";", "Operands must be two numbers or two strings."),
+        ("-\"a\";", "Operand must be a number."),
+        ("missing;", "Undefined variable 'missing'."),
+        ("1();", "Can only call functions and classes."),
+    ];
+    for (source, message) in cases {
+        assert_eq!(runtime_error(source).message, message, "{}", source);
+    }
+}
+
+#[test]
+fn stack_trace() {
+    let source = "fun inner() {\n  return nil + 1;\n}\nfun outer() {\n  return inner();\n}\nouter();";
+    let e = runtime_error(source);
+    let trace: Vec<String> = e.trace.iter().map(|frame| frame.to_string()).collect();
+    assert_eq!(trace, ["[line 2] in inner()", "[line 5] in outer()", "[line 7] in script"]);
+    assert_eq!(e.trace[0].function, "inner");
+}
+
+#[test]
+fn points_at_the_failing_operator() {
+    let e = runtime_error("var a = 1;\nvar b = a +\n  nil;");
+    assert_eq!((e.span.line, e.span.column), (2, 11));
+    assert_eq!(e.source_line, "var b = a +");
+    assert_eq!(e.excerpt().unwrap(), "2 | var b = a +\n  |           ^");
+}
+
+#[test]
+fn underlines_the_whole_name() {
+    let e = runtime_error("print 1;\nprint missing;");
+    assert_eq!(
+        e.to_string(),
+        "Undefined variable 'missing'.\n[line 2] in script\n2 | print missing;\n  |       ^^^^^^^"
+    );
+}
+
+#[test]
+fn excerpts_come_from_the_function_that_failed() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("fun f(x) {\n  return x.field;\n}").unwrap();
+    let e = match interpreter.eval("f(1);") {
+        Err(LoxError::Runtime(e)) => e,
+        other => panic!("expected a runtime error, got {:?}", other),
+    };
+    assert_eq!(e.message, "Only instances have properties.");
+    assert_eq!(e.source_line, "  return x.field;");
+    assert_eq!(e.trace.len(), 2);
+}
@@ -0,0 +1,56 @@
+use rlox::LoxError;
+
+mod common;
+use common::{eval, string};
+
+#[test]
+fn escapes() {
+    assert_eq!(string(r#""tab\there";"#), "tab\there");
+    assert_eq!(string(r#""say \"hi\"\n";"#), "say \"hi\"\n");
+    assert_eq!(string(r#""caf\u{e9}";"#), "caf\u{e9}");
+    assert_eq!(string(r#""a\\" + "b";"#), "a\\b");
+}
+
+#[test]
+fn multi_line_strings() {
+    let source = "var s = \"first\nsecond\";\ns;";
+    assert_eq!(string(source), "first\nsecond");
+
+    // lines after the string are still counted correctly
+    match eval("\"a\nb\nc\";\n\nundefined;") {
+        Err(LoxError::Runtime(e)) => assert_eq!(e.line(), 5, "{}", e),
+        other => panic!("expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn interpolation() {
+    assert_eq!(string(r#"var x = 41; "total: ${x + 1}!";"#), "total: 42!");
+    assert_eq!(string(r#""${1}${2}";"#), "12");
+    assert_eq!(string(r#""${"nested ${"deep"}"}";"#), "nested deep");
+    assert_eq!(string(r#""${nil} ${true} ${1.5}";"#), "nil true 1.5");
+    assert_eq!(
+        string(r#"fun f(n) { return n * 2; } "f(3) = ${f(3)}, done";"#),
+        "f(3) = 6, done"
+    );
+    assert_eq!(
+        string(r#"class A {} var a = A(); "it's ${a}";"#),
+        "it's A instance"
+    );
+}
+
+#[test]
+fn interpolation_binds_like_a_primary() {
+    assert_eq!(string(r#""a${1}" + "b";"#), "a1b");
+    assert_eq!(string(r#"var a = "x"; a = "${a}${a}"; a;"#), "xx");
+}
+
+#[test]
+fn string_errors() {
+    assert!(matches!(eval(r#""\q";"#), Err(LoxError::Compile(_))));
+    assert!(matches!(eval(r#""\u{d800}";"#), Err(LoxError::Compile(_))));
+    assert!(matches!(eval(r#""${1 2}";"#), Err(LoxError::Compile(_))));
+    assert!(matches!(eval(r#""${}";"#), Err(LoxError::Compile(_))));
+    assert!(matches!(eval(r#""${1";"#), Err(LoxError::Compile(_))));
+    assert!(matches!(eval(r#""unterminated"#), Err(LoxError::Compile(_))));
+}
@@ -0,0 +1,174 @@
+//! Runs the Lox test suite in tests/suite against the `rlox` binary.
+//!
+//! Every `.lox` file is a program annotated the way the Crafting Interpreters
+//! suite does it:
+//!
+//! - `// expect: output` is a line the program must print, in order.
+//! - `// expect runtime error: message` means the program prints `message`
+//!   on stderr, followed by a stack trace that starts at the annotated line,
+//!   and exits with 70.
+//! - `// Error at 'x': message` and `// [line N] Error...` are compile errors
+//!   that must be reported, in order, before exiting with 65. The first form
+//!   refers to its own line. Errors marked `[c line N]` are expected of clox
+//!   too, `[java line N]` ones are jlox-only and ignored.
+//!
+//! A file with no error annotations must exit with 0 and print nothing on
+//! stderr. Files listed in tests/suite/skip.txt are expected to fail; a
+//! skipped file that passes fails the run, so the list can't go stale.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const EX_DATAERR: i32 = 65;
+const EX_SOFTWARE: i32 = 70;
+
+#[derive(Debug, Default)]
+struct Expectations {
+    output: Vec<String>,
+    compile_errors: Vec<String>,
+    runtime_error: Option<(String, usize)>,
+}
+
+impl Expectations {
+    fn parse(source: &str) -> Expectations {
+        let mut expected = Expectations::default();
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            // like upstream, an annotation counts wherever it starts on the line
+            for (at, _) in line.match_indices("// ") {
+                let comment = &line[at + 3..];
+                if let Some(output) = comment.strip_prefix("expect: ") {
+                    expected.output.push(output.to_string());
+                } else if let Some(message) = comment.strip_prefix("expect runtime error: ") {
+                    expected.runtime_error = Some((message.to_string(), line_number));
+                } else if comment.starts_with("Error") {
+                    expected.compile_errors.push(format!("[line {}] {}", line_number, comment));
+                } else if let Some(error) = comment.strip_prefix("[c ") {
+                    expected.compile_errors.push(format!("[{}", error));
+                } else if comment.starts_with("[line ") {
+                    expected.compile_errors.push(comment.to_string());
+                } else {
+                    continue;
+                }
+                break;
+            }
+        }
+        expected
+    }
+
+    fn exit_code(&self) -> i32 {
+        if !self.compile_errors.is_empty() {
+            EX_DATAERR
+        } else if self.runtime_error.is_some() {
+            EX_SOFTWARE
+        } else {
+            0
+        }
+    }
+}
+
+/// Runs one test file and returns what went wrong, if anything.
+fn run(path: &Path) -> Vec<String> {
+    let source = fs::read_to_string(path).unwrap();
+    let expected = Expectations::parse(&source);
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .arg(path)
+        .output()
+        .expect("failed to run rlox");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut failures = Vec::new();
+
+    let printed: Vec<&str> = stdout.lines().collect();
+    if printed != expected.output {
+        failures.push(format!("expected output {:?}, got {:?}", expected.output, printed));
+    }
+
+    let errors: Vec<&str> = stderr.lines().collect();
+    if !expected.compile_errors.is_empty() {
+        if errors != expected.compile_errors {
+            failures.push(format!(
+                "expected compile errors {:?}, got {:?}",
+                expected.compile_errors, errors
+            ));
+        }
+    } else if let Some((message, line)) = &expected.runtime_error {
+        let trace = format!("[line {}]", line);
+        if errors.first() != Some(&message.as_str()) {
+            failures.push(format!("expected runtime error {:?}, got {:?}", message, errors));
+        } else if !errors[1..].iter().any(|frame| frame.starts_with(&trace)) {
+            failures.push(format!("expected a stack trace from {}, got {:?}", trace, errors));
+        }
+    } else if !errors.is_empty() {
+        failures.push(format!("unexpected errors {:?}", errors));
+    }
+
+    let code = output.status.code();
+    if code != Some(expected.exit_code()) {
+        failures.push(format!("expected exit code {}, got {:?}", expected.exit_code(), code));
+    }
+    failures
+}
+
+fn collect(dir: &Path, tests: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect(&path, tests);
+        } else if path.extension().is_some_and(|ext| ext == "lox") {
+            tests.push(path);
+        }
+    }
+}
+
+/// Reads skip.txt: one path relative to the suite per line, then `#` and the
+/// reason it's skipped.
+fn skipped(root: &Path) -> BTreeMap<String, String> {
+    let list = fs::read_to_string(root.join("skip.txt")).unwrap_or_default();
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.split_once('#') {
+            Some((path, reason)) => (path.trim().to_string(), reason.trim().to_string()),
+            None => (line.to_string(), String::new()),
+        })
+        .collect()
+}
+
+#[test]
+fn lox_test_suite() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/suite");
+    let mut tests = Vec::new();
+    collect(&root, &mut tests);
+    tests.sort();
+    let mut skip = skipped(&root);
+
+    let (mut passed, mut skipped) = (0, 0);
+    let mut failures = Vec::new();
+    for path in &tests {
+        let name = path.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/");
+        let problems = run(path);
+        match (skip.remove(&name), problems.is_empty()) {
+            (None, true) => passed += 1,
+            (Some(_), false) => skipped += 1,
+            (None, false) => failures.push(format!("{}:\n    {}", name, problems.join("\n    "))),
+            (Some(reason), true) => {
+                failures.push(format!("{}: passes, remove it from skip.txt ({})", name, reason))
+            }
+        }
+    }
+    for name in skip.keys() {
+        failures.push(format!("{}: listed in skip.txt but doesn't exist", name));
+    }
+
+    println!(
+        "{} passed, {} skipped, {} failed, {} total",
+        passed,
+        skipped,
+        failures.len(),
+        tests.len()
+    );
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -0,0 +1,125 @@
+//! Property tests checking `SymTable` against `std::collections::HashMap`
+//! over random sequences of operations.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rlox::symtable::SymTable;
+use rlox::{Interpreter, Value};
+
+mod common;
+use common::XorShift;
+
+fn number(value: Option<&Value>) -> Option<f64> {
+    match value {
+        Some(Value::Number(n)) => Some(*n),
+        Some(other) => panic!("expected a number, got {:?}", other),
+        None => None,
+    }
+}
+
+fn assert_same(table: &SymTable, model: &HashMap<String, f64>) {
+    assert_eq!(table.len(), model.len());
+    assert_eq!(table.is_empty(), model.is_empty());
+
+    let mut entries: Vec<(String, f64)> = table
+        .iter()
+        .map(|(k, v)| (k.to_string(), number(Some(v)).unwrap()))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut expected: Vec<(String, f64)> = model.iter().map(|(k, v)| (k.clone(), *v)).collect();
+    expected.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(entries, expected);
+
+    let mut keys: Vec<String> = table.keys().map(|k| k.to_string()).collect();
+    keys.sort();
+    assert_eq!(keys, expected.into_iter().map(|(k, _)| k).collect::<Vec<_>>());
+}
+
+/// Runs `steps` random operations over `key_space` distinct keys. A small
+/// key space exercises overwrites and tombstone reuse, a large one growth.
+fn check_against_hashmap(seed: u64, key_space: usize, steps: usize) {
+    let mut rng = XorShift(seed);
+    let mut table = SymTable::new();
+    let mut model: HashMap<String, f64> = HashMap::new();
+
+    for step in 0..steps {
+        let key = format!("key{}", rng.below(key_space));
+        match rng.below(10) {
+            0..=4 => {
+                let value = step as f64;
+                let existed = table.set(Rc::from(key.as_str()), Value::Number(value));
+                assert_eq!(existed, model.insert(key, value).is_some());
+            }
+            5..=7 => {
+                assert_eq!(number(table.get(&key)), model.get(&key).copied());
+            }
+            _ => {
+                assert_eq!(table.delete(&key).is_ok(), model.remove(&key).is_some());
+            }
+        }
+        if step % 499 == 0 {
+            assert_same(&table, &model);
+        }
+    }
+    assert_same(&table, &model);
+}
+
+#[test]
+fn matches_hashmap_with_few_keys() {
+    for seed in 1..20 {
+        check_against_hashmap(0x9e37_79b9_7f4a_7c15u64.wrapping_mul(seed), 12, 2000);
+    }
+}
+
+#[test]
+fn matches_hashmap_with_many_keys() {
+    for seed in 1..5 {
+        check_against_hashmap(0x5eed_1234_abcd_0001u64.wrapping_mul(seed), 5000, 20_000);
+    }
+}
+
+#[test]
+fn grows_past_initial_capacity() {
+    let mut table = SymTable::new();
+    for i in 0..10_000 {
+        table.set(Rc::from(format!("k{}", i)), Value::Number(i as f64));
+    }
+    assert_eq!(table.len(), 10_000);
+    for i in 0..10_000 {
+        assert_eq!(number(table.get(&format!("k{}", i))), Some(i as f64));
+    }
+    assert!(table.get("missing").is_none());
+}
+
+#[test]
+fn add_all_copies_every_entry() {
+    let mut from = SymTable::new();
+    let mut to = SymTable::new();
+    for i in 0..50 {
+        from.set(Rc::from(format!("k{}", i)), Value::Number(i as f64));
+    }
+    to.set(Rc::from("k0"), Value::Number(-1.0));
+    to.set(Rc::from("other"), Value::Number(-1.0));
+
+    from.add_all(&mut to);
+    assert_eq!(to.len(), 51);
+    assert_eq!(number(to.get("k0")), Some(0.0));
+    assert_eq!(number(to.get("other")), Some(-1.0));
+}
+
+#[test]
+fn instances_with_many_fields_and_methods() {
+    let mut source = String::from("class Wide {\n");
+    for i in 0..20 {
+        source.push_str(&format!("    m{}() {{ return {}; }}\n", i, i));
+    }
+    source.push_str("}\nvar w = Wide();\n");
+    for i in 0..20 {
+        source.push_str(&format!("w.f{} = {};\n", i, i * 10));
+    }
+    source.push_str("w.f19 + w.m19() + w.f0;");
+
+    let result = Interpreter::new().eval(&source);
+    assert!(matches!(result, Ok(Value::Number(n)) if n == 209.0), "{:?}", result);
+}